@@ -0,0 +1,218 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use std::{
+    convert::TryFrom,
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+use crate::{city::RawCity, City};
+
+/// Every meteogram ever fetched, kept to follow how forecasts evolve.
+///
+/// Entries are appended as JSON lines to `history.jsonl`, and images are
+/// stored once per content under `images/<sha256>.png`, so fetching the same
+/// image again only adds an entry.
+#[derive(Debug, Clone)]
+pub struct Archive {
+    dir: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveEntry {
+    /// Position in the archive, starting at 1.
+    pub id: usize,
+    pub city: City,
+    pub fetched_at: DateTime<Utc>,
+    pub url: String,
+    pub sha256: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Record {
+    city: RawCity,
+    fetched_at: DateTime<Utc>,
+    url: String,
+    sha256: String,
+}
+
+/// Appends a line, starting it on a line of its own if the last one was cut
+/// short, and returns its line number.
+///
+/// Ids are line numbers, so the whole file is read to count them. That is
+/// accepted: the history grows by one short line per fetch, and counting
+/// bytes is far cheaper than the download that comes with each record.
+fn append_line(file: &mut File, line: &[u8]) -> std::io::Result<usize> {
+    let mut lines = 0;
+    let mut last = b'\n';
+    let mut buffer = [0; 8192];
+    file.seek(SeekFrom::Start(0))?;
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        lines += buffer[..read].iter().filter(|&&byte| byte == b'\n').count();
+        last = buffer[read - 1];
+    }
+
+    if last != b'\n' {
+        file.write_all(b"\n")?;
+        lines += 1;
+    }
+
+    file.write_all(line)?;
+    Ok(lines + 1)
+}
+
+impl Archive {
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("meteo").join("archive"))
+    }
+
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        let images = dir.join("images");
+        fs::create_dir_all(&images)
+            .with_context(|| format!("Could not create archive directory {}", images.display()))?;
+
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn history_path(&self) -> PathBuf {
+        self.dir.join("history.jsonl")
+    }
+
+    fn image_path(&self, sha256: &str) -> PathBuf {
+        self.dir.join("images").join(format!("{}.png", sha256))
+    }
+
+    pub fn record(
+        &self,
+        city: &City,
+        url: &Url,
+        image: &[u8],
+        fetched_at: DateTime<Utc>,
+    ) -> Result<ArchiveEntry> {
+        let sha256: String = Sha256::digest(image)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        let image_path = self.image_path(&sha256);
+        if !image_path.exists() {
+            // Renamed into place so a concurrent reader never sees half an image.
+            let temp_path = image_path.with_extension(format!("{}.tmp", process::id()));
+            fs::write(&temp_path, image)
+                .and_then(|_| fs::rename(&temp_path, &image_path))
+                .with_context(|| format!("Could not write {}", image_path.display()))?;
+        }
+
+        let record = Record {
+            city: RawCity::from(city),
+            fetched_at,
+            url: url.to_string(),
+            sha256,
+        };
+
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+
+        // The history stays locked while it is counted and appended to, so
+        // concurrent recorders can't take the same id.
+        let path = self.history_path();
+        let id = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| {
+                file.lock()?;
+                let id = append_line(&mut file, &line)?;
+                file.unlock()?;
+                Ok(id)
+            })
+            .with_context(|| format!("Could not write {}", path.display()))?;
+
+        Ok(ArchiveEntry {
+            id,
+            city: city.clone(),
+            fetched_at: record.fetched_at,
+            url: record.url,
+            sha256: record.sha256,
+        })
+    }
+
+    /// All entries, oldest first. Lines that can't be read, like one cut
+    /// short by an interrupted write, are skipped with a warning; the other
+    /// entries keep their line number as id.
+    pub fn entries(&self) -> Result<Vec<ArchiveEntry>> {
+        let path = self.history_path();
+        let contents = match fs::read(&path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => {
+                return Err(error).with_context(|| format!("Could not read {}", path.display()))
+            }
+        };
+
+        let entries = String::from_utf8_lossy(&contents)
+            .lines()
+            .enumerate()
+            .filter_map(|(i, line)| {
+                let entry = serde_json::from_str::<Record>(line)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|record| {
+                        Ok(ArchiveEntry {
+                            id: i + 1,
+                            city: City::try_from(record.city)?,
+                            fetched_at: record.fetched_at,
+                            url: record.url,
+                            sha256: record.sha256,
+                        })
+                    });
+
+                match entry {
+                    Ok(entry) => Some(entry),
+                    Err(error) => {
+                        eprintln!(
+                            "Skipping invalid archive entry {} in {}: {:#}",
+                            i + 1,
+                            path.display(),
+                            error
+                        );
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        Ok(entries)
+    }
+
+    /// Entries for one city, oldest first.
+    pub fn history(&self, city: &City) -> Result<Vec<ArchiveEntry>> {
+        let mut entries = self.entries()?;
+        entries.retain(|entry| entry.city.id == city.id);
+        Ok(entries)
+    }
+
+    pub fn entry(&self, id: usize) -> Result<Option<ArchiveEntry>> {
+        Ok(self.entries()?.into_iter().find(|entry| entry.id == id))
+    }
+
+    pub fn image(&self, entry: &ArchiveEntry) -> Result<Vec<u8>> {
+        let path = self.image_path(&entry.sha256);
+        fs::read(&path).with_context(|| format!("Could not read {}", path.display()))
+    }
+}
@@ -0,0 +1,217 @@
+use anyhow::{Context, Result};
+use reqwest::{
+    header::{HeaderMap, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    Url,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+    Search,
+    ForecastPage,
+    Meteogram,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Serve fresh entries and revalidate stale ones.
+    Use,
+    /// Ignore stored entries, but store whatever gets downloaded.
+    Refresh,
+}
+
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+    mode: CacheMode,
+    search_ttl: Duration,
+    forecast_page_ttl: Duration,
+    meteogram_ttl: Duration,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Metadata {
+    url: String,
+    fetched_at: u64,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+#[derive(Debug)]
+pub(crate) enum Lookup {
+    Fresh(Vec<u8>),
+    Stale(Stale),
+    Miss,
+}
+
+#[derive(Debug)]
+pub(crate) struct Stale {
+    metadata: Metadata,
+    body: Vec<u8>,
+}
+
+impl Stale {
+    pub(crate) fn conditional_headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        let etag = self.metadata.etag.as_deref();
+        if let Some(value) = etag.and_then(|v| HeaderValue::from_str(v).ok()) {
+            headers.insert(IF_NONE_MATCH, value);
+        }
+
+        let last_modified = self.metadata.last_modified.as_deref();
+        if let Some(value) = last_modified.and_then(|v| HeaderValue::from_str(v).ok()) {
+            headers.insert(IF_MODIFIED_SINCE, value);
+        }
+
+        headers
+    }
+}
+
+impl Cache {
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("meteo"))
+    }
+
+    pub fn open(dir: impl Into<PathBuf>, mode: CacheMode) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Could not create cache directory {}", dir.display()))?;
+
+        Ok(Self {
+            dir,
+            mode,
+            search_ttl: Duration::from_secs(7 * 24 * 60 * 60),
+            forecast_page_ttl: Duration::from_secs(30 * 60),
+            meteogram_ttl: Duration::from_secs(30 * 60),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn ttl(&self, resource: Resource) -> Duration {
+        match resource {
+            Resource::Search => self.search_ttl,
+            Resource::ForecastPage => self.forecast_page_ttl,
+            Resource::Meteogram => self.meteogram_ttl,
+        }
+    }
+
+    pub fn set_ttl(&mut self, resource: Resource, ttl: Duration) {
+        match resource {
+            Resource::Search => self.search_ttl = ttl,
+            Resource::ForecastPage => self.forecast_page_ttl = ttl,
+            Resource::Meteogram => self.meteogram_ttl = ttl,
+        }
+    }
+
+    /// Each entry is one file, the metadata as a JSON line followed by the
+    /// body, so that both are always replaced together.
+    fn path(&self, url: &Url) -> PathBuf {
+        let key: String = Sha256::digest(url.as_str().as_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        self.dir.join(format!("{}.entry", key))
+    }
+
+    /// Unreadable or corrupted entries are treated as misses.
+    pub(crate) fn lookup(&self, url: &Url, resource: Resource) -> Lookup {
+        if self.mode == CacheMode::Refresh {
+            return Lookup::Miss;
+        }
+
+        let entry = fs::read(self.path(url)).ok();
+        let (metadata, body) = match entry.as_deref().and_then(parse_entry) {
+            Some((metadata, body)) if metadata.url == url.as_str() => (metadata, body.to_vec()),
+            _ => return Lookup::Miss,
+        };
+
+        let age = now().saturating_sub(metadata.fetched_at);
+        if age < self.ttl(resource).as_secs() {
+            Lookup::Fresh(body)
+        } else {
+            Lookup::Stale(Stale { metadata, body })
+        }
+    }
+
+    pub(crate) fn store(&self, url: &Url, headers: &HeaderMap, body: &[u8]) -> Result<()> {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value: &HeaderValue| value.to_str().ok())
+                .map(str::to_owned)
+        };
+
+        let metadata = Metadata {
+            url: url.to_string(),
+            fetched_at: now(),
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        };
+
+        self.write(&metadata, body)
+    }
+
+    /// Marks a stale entry as fresh again after the server answered 304.
+    pub(crate) fn revalidated(&self, stale: Stale) -> Result<Vec<u8>> {
+        let metadata = Metadata {
+            fetched_at: now(),
+            ..stale.metadata
+        };
+
+        self.write(&metadata, &stale.body)?;
+        Ok(stale.body)
+    }
+
+    /// Writes to a temporary file and renames it into place, so concurrent
+    /// readers see either the old entry or the new one, never half of it.
+    fn write(&self, metadata: &Metadata, body: &[u8]) -> Result<()> {
+        static WRITES: AtomicUsize = AtomicUsize::new(0);
+
+        let url = Url::parse(&metadata.url)?;
+        let path = self.path(&url);
+        let temp_path = path.with_extension(format!(
+            "{}.{}.tmp",
+            process::id(),
+            WRITES.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let mut entry = serde_json::to_vec(metadata)?;
+        entry.push(b'\n');
+        entry.extend_from_slice(body);
+
+        fs::write(&temp_path, entry)
+            .and_then(|_| fs::rename(&temp_path, &path))
+            .inspect_err(|_| {
+                let _ = fs::remove_file(&temp_path);
+            })
+            .with_context(|| format!("Could not write cache entry {}", path.display()))
+    }
+}
+
+fn parse_entry(entry: &[u8]) -> Option<(Metadata, &[u8])> {
+    let newline = entry.iter().position(|&byte| byte == b'\n')?;
+    let metadata = serde_json::from_slice(&entry[..newline]).ok()?;
+
+    Some((metadata, &entry[newline + 1..]))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,195 @@
+use anyhow::{Context, Result};
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize, Serializer};
+
+use std::{convert::TryFrom, error::Error, fmt::Display};
+
+/// A city as returned by the CPTEC autocomplete endpoint, e.g.
+/// `{"id": "4564", "label": "Florian%C3%B3polis%2FSC", "custom": "sc/florianopolis", ...}`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RawCity {
+    pub id: String,
+    pub label: String,
+    #[serde(default)]
+    pub value: String,
+    pub custom: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "CityRepr")]
+pub struct City {
+    pub id: u32,
+    /// City name without the state, e.g. "Florianópolis".
+    pub name: String,
+    /// State abbreviation (unidade federativa), e.g. "SC".
+    pub uf: String,
+    /// Path of the forecast page, e.g. `["sc", "florianopolis"]`.
+    pub path: Vec<String>,
+}
+
+/// Either form a city is read from: the raw autocomplete one, or the decoded
+/// one `City` serializes to, so serialized cities can be read back.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CityRepr {
+    Raw(RawCity),
+    Decoded {
+        id: u32,
+        name: String,
+        uf: String,
+        custom: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CityError {
+    InvalidId(String),
+    MissingUf(String),
+    EmptyPath(String),
+}
+
+impl Display for CityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CityError::InvalidId(id) => write!(f, "City id {:?} is not a number", id),
+            CityError::MissingUf(label) => write!(f, "City label {:?} has no state", label),
+            CityError::EmptyPath(label) => write!(f, "City {:?} has no forecast path", label),
+        }
+    }
+}
+
+impl Error for CityError {}
+
+impl City {
+    /// The label as shown to users, e.g. "Florianópolis/SC".
+    pub fn label(&self) -> String {
+        format!("{}/{}", self.name, self.uf)
+    }
+
+    /// The path as sent by CPTEC, e.g. "sc/florianopolis".
+    pub fn custom(&self) -> String {
+        self.path.join("/")
+    }
+}
+
+fn split_path(custom: &str) -> Vec<String> {
+    custom
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+impl TryFrom<RawCity> for City {
+    type Error = CityError;
+
+    fn try_from(raw: RawCity) -> Result<Self, Self::Error> {
+        let label = percent_decode_str(&raw.label)
+            .decode_utf8_lossy()
+            .replace('+', " ");
+
+        let id = raw
+            .id
+            .trim()
+            .parse()
+            .map_err(|_| CityError::InvalidId(raw.id.clone()))?;
+
+        let (name, uf) = label
+            .rsplit_once('/')
+            .or_else(|| label.rsplit_once(" - "))
+            .map(|(name, uf)| (name.trim(), uf.trim()))
+            .filter(|(name, uf)| !name.is_empty() && !uf.is_empty())
+            .ok_or_else(|| CityError::MissingUf(label.clone()))?;
+
+        let path = split_path(&raw.custom);
+        if path.is_empty() {
+            return Err(CityError::EmptyPath(label));
+        }
+
+        Ok(City {
+            id,
+            name: name.to_owned(),
+            uf: uf.to_uppercase(),
+            path,
+        })
+    }
+}
+
+impl TryFrom<CityRepr> for City {
+    type Error = CityError;
+
+    fn try_from(repr: CityRepr) -> Result<Self, Self::Error> {
+        let (id, name, uf, custom) = match repr {
+            CityRepr::Raw(raw) => return City::try_from(raw),
+            CityRepr::Decoded {
+                id,
+                name,
+                uf,
+                custom,
+            } => (id, name, uf, custom),
+        };
+
+        let label = format!("{}/{}", name, uf);
+        if name.trim().is_empty() || uf.trim().is_empty() {
+            return Err(CityError::MissingUf(label));
+        }
+
+        let path = split_path(&custom);
+        if path.is_empty() {
+            return Err(CityError::EmptyPath(label));
+        }
+
+        Ok(City {
+            id,
+            name: name.trim().to_owned(),
+            uf: uf.trim().to_uppercase(),
+            path,
+        })
+    }
+}
+
+impl From<&City> for RawCity {
+    fn from(city: &City) -> Self {
+        RawCity {
+            id: city.id.to_string(),
+            label: city.label(),
+            value: city.label(),
+            custom: city.custom(),
+        }
+    }
+}
+
+impl Display for City {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+impl Serialize for City {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Decoded<'a> {
+            id: u32,
+            name: &'a str,
+            uf: &'a str,
+            custom: String,
+        }
+
+        Decoded {
+            id: self.id,
+            name: &self.name,
+            uf: &self.uf,
+            custom: self.custom(),
+        }
+        .serialize(serializer)
+    }
+}
+
+pub(crate) fn parse_cities(json: &[u8]) -> Result<Vec<City>> {
+    let raw: Vec<RawCity> =
+        serde_json::from_slice(json).context("Unexpected autocomplete payload")?;
+
+    raw.into_iter()
+        .map(|raw| City::try_from(raw).context("Unexpected autocomplete payload"))
+        .collect()
+}
@@ -0,0 +1,81 @@
+use anyhow::{bail, Context, Result};
+use meteo::{City, CptecClient, Forecast, ForecastChange};
+
+use super::Exit;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Parses percentages like `60%` or `60`.
+pub fn parse_percentage(value: &str) -> Result<u8> {
+    let number = value.trim().trim_end_matches('%');
+    let percentage: u8 = number
+        .parse()
+        .with_context(|| format!("Invalid percentage {:?}", value))?;
+
+    if percentage > 100 {
+        bail!("Percentage {:?} is above 100%", value);
+    }
+
+    Ok(percentage)
+}
+
+pub fn default_state_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("meteo").join("forecasts"))
+}
+
+/// Compares the forecast of a city against the one seen on the previous run,
+/// kept as `<id>.json` in `state_dir`, and prints what changed. Fails with
+/// `Exit::ForecastChanged` if anything did.
+pub fn run(
+    client: &CptecClient,
+    city: &City,
+    state_dir: &Path,
+    rain_above: Option<u8>,
+) -> Result<()> {
+    let forecast = client.forecast(city)?;
+
+    let path = state_dir.join(format!("{}.json", city.id));
+    let previous = match fs::read(&path) {
+        Ok(json) => serde_json::from_slice(&json)
+            .with_context(|| format!("Invalid stored forecast {}", path.display()))?,
+        Err(_) => Forecast::default(),
+    };
+
+    let changes = forecast.changes_since(&previous, rain_above);
+
+    fs::create_dir_all(state_dir)
+        .with_context(|| format!("Could not create {}", state_dir.display()))?;
+    fs::write(&path, serde_json::to_vec(&forecast)?)
+        .with_context(|| format!("Could not write {}", path.display()))?;
+
+    for change in &changes {
+        match change {
+            ForecastChange::RainAbove {
+                date,
+                previous,
+                current,
+            } => println!(
+                "{} {}: chuva {}% (antes {}), acima de {}%",
+                city,
+                date,
+                current,
+                previous.map_or("-".to_owned(), |rain| format!("{}%", rain)),
+                rain_above.unwrap_or_default()
+            ),
+            ForecastChange::Condition {
+                date,
+                previous,
+                current,
+            } => println!("{} {}: {} -> {}", city, date, previous, current),
+        }
+    }
+
+    if !changes.is_empty() {
+        return Err(Exit::ForecastChanged.into());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,141 @@
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use meteo::{Archive, City, CptecClient};
+use serde::Deserialize;
+
+use super::{
+    config::Config,
+    history::fetch_meteogram,
+    save::{save_meteogram, Overwrite},
+    template,
+};
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+#[derive(Deserialize)]
+struct BatchFile {
+    cities: Vec<String>,
+}
+
+/// Reads either a TOML file with a `cities` list, or one city per line.
+/// Blank lines and lines starting with `#` are skipped.
+pub fn read_cities(path: &Path) -> Result<Vec<String>> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("Could not read {}", path.display()))?;
+
+    if path.extension().is_some_and(|ext| ext == "toml") {
+        let file: BatchFile = toml::from_str(&contents)
+            .with_context(|| format!("Invalid batch file {}", path.display()))?;
+        return Ok(file.cities);
+    }
+
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_owned)
+        .collect())
+}
+
+/// A favourite alias, or else the best match of a search.
+pub fn resolve(client: &CptecClient, config: &Config, name: &str) -> Result<City> {
+    if let Some(city) = config.favourite(name)? {
+        return Ok(city);
+    }
+
+    client
+        .search(name)?
+        .into_iter()
+        .next()
+        .context("nenhuma cidade encontrada")
+}
+
+/// Where and how the meteograms of a batch are saved.
+pub struct Output<'a> {
+    pub dir: &'a Path,
+    pub name_template: &'a str,
+    pub overwrite: &'a Overwrite,
+}
+
+fn fetch(
+    client: &CptecClient,
+    config: &Config,
+    archive: Option<&Archive>,
+    name: &str,
+    output: &Output,
+) -> Result<(PathBuf, bool)> {
+    let city = resolve(client, config, name)?;
+    let meteogram = fetch_meteogram(client, archive, &city)?;
+
+    let file_name = template::render(output.name_template, &city, &Local::now(), "png")?;
+    let path = output.dir.join(file_name);
+    let written = save_meteogram(&meteogram, &path, output.overwrite)?;
+
+    Ok((path, written))
+}
+
+pub fn run(
+    client: &CptecClient,
+    config: &Config,
+    archive: Option<&Archive>,
+    names: &[String],
+    output: &Output,
+    jobs: usize,
+) -> Result<()> {
+    let out_dir = output.dir;
+    fs::create_dir_all(out_dir)
+        .with_context(|| format!("Could not create {}", out_dir.display()))?;
+
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::new());
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, names.len().max(1)) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let name = match names.get(i) {
+                    Some(name) => name,
+                    None => break,
+                };
+
+                let result = fetch(client, config, archive, name, output);
+                results.lock().unwrap().push((i, result));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(i, _)| *i);
+
+    let mut failures = 0;
+    for (i, result) in results {
+        match result {
+            Ok((path, true)) => println!("ok    {} -> {}", names[i], path.display()),
+            Ok((path, false)) => println!("mant  {} -> {}", names[i], path.display()),
+            Err(error) => {
+                failures += 1;
+                println!("erro  {}: {:#}", names[i], error);
+            }
+        }
+    }
+
+    println!(
+        "\n{} baixados, {} falharam",
+        names.len() - failures,
+        failures
+    );
+
+    if failures > 0 {
+        bail!("{} of {} cities failed", failures, names.len());
+    }
+
+    Ok(())
+}
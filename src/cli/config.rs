@@ -0,0 +1,122 @@
+use anyhow::{bail, Context, Result};
+use meteo::{City, RawCity};
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::BTreeMap,
+    convert::TryFrom,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Config {
+    /// Alias of the favourite used when no query is given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+
+    #[serde(default)]
+    pub favourites: BTreeMap<String, Favourite>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Favourite {
+    pub id: String,
+    pub label: String,
+    pub custom: String,
+}
+
+impl From<&City> for Favourite {
+    fn from(city: &City) -> Self {
+        Self {
+            id: city.id.to_string(),
+            label: city.label(),
+            custom: city.custom(),
+        }
+    }
+}
+
+impl Favourite {
+    pub fn city(&self) -> Result<City> {
+        let raw = RawCity {
+            id: self.id.clone(),
+            label: self.label.clone(),
+            value: String::new(),
+            custom: self.custom.clone(),
+        };
+
+        Ok(City::try_from(raw)?)
+    }
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("meteo").join("config.toml"))
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Self::default())
+            }
+            Err(error) => {
+                return Err(error).with_context(|| format!("Could not read {}", path.display()))
+            }
+        };
+
+        toml::from_str(&contents).with_context(|| format!("Invalid config file {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(path, toml::to_string(self)?)
+            .with_context(|| format!("Could not write {}", path.display()))
+    }
+
+    pub fn favourite(&self, alias: &str) -> Result<Option<City>> {
+        let favourite = match self.favourites.get(alias) {
+            Some(favourite) => favourite,
+            None => return Ok(None),
+        };
+
+        let city = favourite
+            .city()
+            .with_context(|| format!("Invalid favourite {}", alias))?;
+
+        Ok(Some(city))
+    }
+
+    pub fn default_favourite(&self) -> Result<City> {
+        let alias = match &self.default {
+            Some(alias) => alias,
+            None => bail!("No query given and no default favourite set"),
+        };
+
+        self.favourite(alias)?
+            .with_context(|| format!("Default favourite {} does not exist", alias))
+    }
+
+    pub fn add_favourite(&mut self, alias: &str, city: &City, default: bool) {
+        self.favourites.insert(alias.to_owned(), city.into());
+
+        if default || self.default.is_none() {
+            self.default = Some(alias.to_owned());
+        }
+    }
+
+    pub fn remove_favourite(&mut self, alias: &str) -> Result<()> {
+        if self.favourites.remove(alias).is_none() {
+            bail!("No favourite named {}", alias);
+        }
+
+        if self.default.as_deref() == Some(alias) {
+            self.default = None;
+        }
+
+        Ok(())
+    }
+}
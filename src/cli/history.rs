@@ -0,0 +1,23 @@
+use anyhow::Result;
+use chrono::Utc;
+use meteo::{Archive, City, CptecClient};
+
+/// Fetches the meteogram of a city, recording it in the archive if given. A
+/// meteogram that can't be archived is still returned, with a warning.
+pub fn fetch_meteogram(
+    client: &CptecClient,
+    archive: Option<&Archive>,
+    city: &City,
+) -> Result<Vec<u8>> {
+    let archive = match archive {
+        Some(archive) => archive,
+        None => return client.meteogram(city),
+    };
+
+    let (url, meteogram) = client.meteogram_with_url(city)?;
+    if let Err(error) = archive.record(city, &url, &meteogram, Utc::now()) {
+        eprintln!("Could not archive the meteogram: {:#}", error);
+    }
+
+    Ok(meteogram)
+}
@@ -0,0 +1,187 @@
+use anyhow::Error;
+use meteo::{
+    scrape_forecast, scrape_meteogram_url, City, CptecClient, DailyForecast, Forecast, ScrapeError,
+};
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    sync::Mutex,
+};
+
+#[derive(Default)]
+struct CityMetrics {
+    forecast: Option<Forecast>,
+    fetch_errors: u64,
+    missing_meteogram_errors: u64,
+}
+
+/// Forecast values and scrape health by city, in the Prometheus text format.
+#[derive(Default)]
+pub struct Metrics {
+    cities: Mutex<BTreeMap<u32, (City, CityMetrics)>>,
+}
+
+type Gauge = fn(&DailyForecast) -> Option<f64>;
+type Counter = fn(&CityMetrics) -> u64;
+
+const GAUGES: &[(&str, &str, Gauge)] = &[
+    (
+        "meteo_temperature_max_celsius",
+        "Forecast maximum temperature.",
+        |day| day.max_temperature.map(f64::from),
+    ),
+    (
+        "meteo_temperature_min_celsius",
+        "Forecast minimum temperature.",
+        |day| day.min_temperature.map(f64::from),
+    ),
+    (
+        "meteo_rain_probability_percent",
+        "Forecast chance of rain.",
+        |day| day.rain_probability.map(f64::from),
+    ),
+    (
+        "meteo_humidity_max_percent",
+        "Forecast maximum relative humidity.",
+        |day| day.max_humidity.map(f64::from),
+    ),
+    (
+        "meteo_humidity_min_percent",
+        "Forecast minimum relative humidity.",
+        |day| day.min_humidity.map(f64::from),
+    ),
+];
+
+const COUNTERS: &[(&str, &str, Counter)] = &[
+    (
+        "meteo_fetch_errors_total",
+        "Failed requests to CPTEC or unreadable pages.",
+        |metrics| metrics.fetch_errors,
+    ),
+    (
+        "meteo_missing_meteogram_errors_total",
+        "Forecast pages without a meteogram.",
+        |metrics| metrics.missing_meteogram_errors,
+    ),
+];
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn city_labels(city: &City) -> String {
+    format!(
+        "id=\"{}\",city=\"{}\",uf=\"{}\"",
+        city.id,
+        escape(&city.name),
+        escape(&city.uf)
+    )
+}
+
+impl Metrics {
+    fn update(&self, city: &City, update: impl FnOnce(&mut CityMetrics)) {
+        let mut cities = self.cities.lock().unwrap();
+        let (_, metrics) = cities
+            .entry(city.id)
+            .or_insert_with(|| (city.clone(), CityMetrics::default()));
+        update(metrics);
+    }
+
+    pub fn record_forecast(&self, city: &City, forecast: &Forecast) {
+        self.update(city, |metrics| metrics.forecast = Some(forecast.clone()));
+    }
+
+    /// Counts a failed fetch, telling apart pages that lack the meteogram.
+    pub fn record_error(&self, city: &City, error: &Error) {
+        let missing_meteogram =
+            error.downcast_ref::<ScrapeError>() == Some(&ScrapeError::MissingMeteogram);
+
+        self.update(city, |metrics| {
+            if missing_meteogram {
+                metrics.missing_meteogram_errors += 1;
+            } else {
+                metrics.fetch_errors += 1;
+            }
+        });
+    }
+
+    /// Fetches the forecast page of a city once, recording its forecast and
+    /// at most one error, whether the page could not be fetched, has no
+    /// forecast or no longer links the meteogram.
+    pub fn collect(&self, client: &CptecClient, city: &City) {
+        let page_contents = match client.forecast_page(city) {
+            Ok(page_contents) => page_contents,
+            Err(error) => return self.record_error(city, &error),
+        };
+
+        let forecast = scrape_forecast(&page_contents);
+        if forecast.days.is_empty() {
+            return self.record_error(city, &ScrapeError::MissingForecast.into());
+        }
+        self.record_forecast(city, &forecast);
+
+        if scrape_meteogram_url(&page_contents).is_none() {
+            self.record_error(city, &ScrapeError::MissingMeteogram.into());
+        }
+    }
+
+    pub fn render(&self, out: &mut impl Write) -> io::Result<()> {
+        let cities = self.cities.lock().unwrap();
+
+        for (name, help, value) in GAUGES {
+            writeln!(out, "# HELP {} {}", name, help)?;
+            writeln!(out, "# TYPE {} gauge", name)?;
+
+            for (city, metrics) in cities.values() {
+                let days = metrics.forecast.iter().flat_map(|forecast| &forecast.days);
+                for (offset, day) in days.enumerate() {
+                    if let Some(value) = value(day) {
+                        writeln!(
+                            out,
+                            "{}{{{},day=\"{}\"}} {}",
+                            name,
+                            city_labels(city),
+                            offset,
+                            value
+                        )?;
+                    }
+                }
+            }
+        }
+
+        // The date changes every day, so it is kept out of the gauges' labels
+        // and only tells which date each day offset currently stands for.
+        writeln!(
+            out,
+            "# HELP meteo_forecast_day_info Date of each forecast day."
+        )?;
+        writeln!(out, "# TYPE meteo_forecast_day_info gauge")?;
+        for (city, metrics) in cities.values() {
+            let days = metrics.forecast.iter().flat_map(|forecast| &forecast.days);
+            for (offset, day) in days.enumerate() {
+                writeln!(
+                    out,
+                    "meteo_forecast_day_info{{{},day=\"{}\",date=\"{}\"}} 1",
+                    city_labels(city),
+                    offset,
+                    escape(&day.date)
+                )?;
+            }
+        }
+
+        for (name, help, value) in COUNTERS {
+            writeln!(out, "# HELP {} {}", name, help)?;
+            writeln!(out, "# TYPE {} counter", name)?;
+
+            for (city, metrics) in cities.values() {
+                writeln!(out, "{}{{{}}} {}", name, city_labels(city), value(metrics))?;
+            }
+        }
+
+        Ok(())
+    }
+}
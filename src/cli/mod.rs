@@ -0,0 +1,45 @@
+pub mod alert;
+pub mod batch;
+pub mod config;
+pub mod history;
+pub mod metrics;
+pub mod mqtt;
+pub mod output;
+pub mod save;
+pub mod select;
+pub mod serve;
+pub mod template;
+pub mod terminal;
+pub mod watch;
+
+use std::{error::Error, fmt::Display, process::ExitCode};
+
+/// Outcomes that end the program early with a specific exit code.
+#[derive(Debug)]
+pub enum Exit {
+    NoCitiesFound,
+    ForecastChanged,
+    Quit,
+}
+
+impl Exit {
+    pub fn code(&self) -> ExitCode {
+        match self {
+            Exit::NoCitiesFound => ExitCode::from(3),
+            Exit::ForecastChanged => ExitCode::from(4),
+            Exit::Quit => ExitCode::SUCCESS,
+        }
+    }
+}
+
+impl Display for Exit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Exit::NoCitiesFound => write!(f, "nenhuma cidade encontrada"),
+            Exit::ForecastChanged => write!(f, "a previsão mudou"),
+            Exit::Quit => Ok(()),
+        }
+    }
+}
+
+impl Error for Exit {}
@@ -0,0 +1,301 @@
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use meteo::{scrape_forecast, City, CptecClient, ScrapeError};
+use rumqttc::{Client, Connection, Event, MqttOptions, Outgoing, Packet, QoS};
+use serde_json::{json, Value};
+
+use std::{
+    sync::mpsc,
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// How long to wait before polling the broker again after an error, which
+/// makes the client reconnect.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+pub struct Message {
+    pub topic: String,
+    pub payload: String,
+    pub retain: bool,
+}
+
+pub struct Topics {
+    /// Prefix of the state topics, e.g. `meteo/4564/state`.
+    pub prefix: String,
+    /// Prefix Home Assistant listens to for discovery.
+    pub discovery_prefix: String,
+}
+
+/// The sensors each city shows up as in Home Assistant: key, name, unit,
+/// device class and the field of today's forecast they read.
+const SENSORS: &[(&str, &str, Option<&str>, Option<&str>)] = &[
+    (
+        "max_temperature",
+        "Temperatura máxima",
+        Some("°C"),
+        Some("temperature"),
+    ),
+    (
+        "min_temperature",
+        "Temperatura mínima",
+        Some("°C"),
+        Some("temperature"),
+    ),
+    ("rain_probability", "Chance de chuva", Some("%"), None),
+    (
+        "max_humidity",
+        "Umidade máxima",
+        Some("%"),
+        Some("humidity"),
+    ),
+    (
+        "min_humidity",
+        "Umidade mínima",
+        Some("%"),
+        Some("humidity"),
+    ),
+    ("condition", "Condição", None, None),
+];
+
+impl Topics {
+    fn state(&self, city: &City) -> String {
+        format!("{}/{}/state", self.prefix, city.id)
+    }
+
+    /// Retained discovery configs, one per sensor, all grouped in a device
+    /// named after the city and reading the same state topic.
+    pub fn discovery(&self, city: &City) -> Vec<Message> {
+        let device = json!({
+            "identifiers": [format!("meteo_{}", city.id)],
+            "name": city.label(),
+            "manufacturer": "CPTEC/INPE",
+        });
+
+        let mut sensors: Vec<(&str, String, Value)> = SENSORS
+            .iter()
+            .map(|(key, name, unit, device_class)| {
+                let config = json!({
+                    "unit_of_measurement": unit,
+                    "device_class": device_class,
+                    "value_template": format!("{{{{ value_json.today.{} }}}}", key),
+                    "json_attributes_topic": self.state(city),
+                    "json_attributes_template": "{{ {'days': value_json.days} | tojson }}",
+                });
+                (*key, format!("{} {}", name, city.label()), config)
+            })
+            .collect();
+
+        sensors.push((
+            "meteogram_url",
+            format!("Meteograma {}", city.label()),
+            json!({ "value_template": "{{ value_json.meteogram_url }}" }),
+        ));
+
+        sensors
+            .into_iter()
+            .map(|(key, name, mut config)| {
+                let config_map = config.as_object_mut().expect("sensor config is an object");
+                config_map.retain(|_, value| !value.is_null());
+                config_map.insert("name".to_owned(), json!(name));
+                config_map.insert(
+                    "unique_id".to_owned(),
+                    json!(format!("meteo_{}_{}", city.id, key)),
+                );
+                config_map.insert("state_topic".to_owned(), json!(self.state(city)));
+                config_map.insert("device".to_owned(), device.clone());
+
+                Message {
+                    topic: format!(
+                        "{}/sensor/meteo_{}/{}/config",
+                        self.discovery_prefix, city.id, key
+                    ),
+                    payload: config.to_string(),
+                    retain: true,
+                }
+            })
+            .collect()
+    }
+
+    /// The current forecast of a city, or `None` if it could not be fetched,
+    /// read along with the meteogram URL from a single fetch of its page.
+    pub fn state_message(&self, client: &CptecClient, city: &City) -> Option<Message> {
+        let page_contents = match client.forecast_page(city) {
+            Ok(page_contents) => page_contents,
+            Err(error) => {
+                eprintln!("{}: {:#}", city, error);
+                return None;
+            }
+        };
+
+        let forecast = scrape_forecast(&page_contents);
+        if forecast.days.is_empty() {
+            eprintln!("{}: {}", city, ScrapeError::MissingForecast);
+            return None;
+        }
+
+        let meteogram_url = match client.meteogram_url_from_page(&page_contents) {
+            Ok(url) => Some(url.to_string()),
+            Err(error) => {
+                eprintln!("{}: {:#}", city, error);
+                None
+            }
+        };
+
+        let state = json!({
+            "city": city.label(),
+            "updated_at": Utc::now().to_rfc3339(),
+            "forecast_url": client.forecast_url(city).ok().map(|url| url.to_string()),
+            "meteogram_url": meteogram_url,
+            "today": forecast.days.first(),
+            "days": forecast.days,
+        });
+
+        Some(Message {
+            topic: self.state(city),
+            payload: state.to_string(),
+            retain: true,
+        })
+    }
+}
+
+/// Where the messages go: a broker, or stdout for a dry run.
+pub enum Publisher {
+    DryRun,
+    Broker {
+        client: Client,
+        connection: JoinHandle<()>,
+    },
+}
+
+fn parse_broker(broker: &str) -> Result<(String, u16)> {
+    match broker.rsplit_once(':') {
+        Some((host, port)) => {
+            let port = port
+                .parse()
+                .with_context(|| format!("Invalid broker port in {:?}", broker))?;
+            Ok((host.to_owned(), port))
+        }
+        None => Ok((broker.to_owned(), 1883)),
+    }
+}
+
+/// Polls the connection until the client disconnects, reporting errors but
+/// otherwise carrying on, since the client reconnects on the next poll.
+fn drive(mut connection: Connection, connected: mpsc::Sender<Result<()>>) {
+    let mut connected = Some(connected);
+
+    for event in connection.iter() {
+        match event {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                if let Some(connected) = connected.take() {
+                    let _ = connected.send(Ok(()));
+                }
+            }
+            Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
+            Ok(_) => {}
+            Err(error) => {
+                if let Some(connected) = connected.take() {
+                    let _ = connected.send(Err(error.into()));
+                    break;
+                }
+
+                eprintln!("mqtt: {}", error);
+                thread::sleep(RECONNECT_DELAY);
+            }
+        }
+    }
+}
+
+impl Publisher {
+    /// Connects to the broker, failing if the first attempt does.
+    pub fn connect(broker: &str, credentials: Option<(&str, &str)>) -> Result<Self> {
+        let (host, port) = parse_broker(broker)?;
+
+        let mut options = MqttOptions::new(format!("meteo-{}", std::process::id()), host, port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let Some((username, password)) = credentials {
+            options.set_credentials(username, password);
+        }
+
+        let (client, connection) = Client::new(options, 16);
+        let (connected_tx, connected_rx) = mpsc::channel();
+        let connection = thread::spawn(move || drive(connection, connected_tx));
+
+        match connected_rx.recv() {
+            Ok(Ok(())) => Ok(Publisher::Broker { client, connection }),
+            Ok(Err(error)) => {
+                Err(error).with_context(|| format!("Could not connect to {}", broker))
+            }
+            Err(_) => bail!("Could not connect to {}", broker),
+        }
+    }
+
+    pub fn publish(&mut self, message: &Message) -> Result<()> {
+        match self {
+            Publisher::DryRun => {
+                println!("{} {}", message.topic, message.payload);
+                Ok(())
+            }
+            Publisher::Broker { client, .. } => {
+                client
+                    .publish(
+                        message.topic.as_str(),
+                        QoS::AtLeastOnce,
+                        message.retain,
+                        message.payload.as_bytes(),
+                    )
+                    .with_context(|| format!("Could not publish to {}", message.topic))?;
+                Ok(())
+            }
+        }
+    }
+
+    pub fn disconnect(self) -> Result<()> {
+        if let Publisher::Broker {
+            mut client,
+            connection,
+        } = self
+        {
+            client.disconnect()?;
+            let _ = connection.join();
+        }
+
+        Ok(())
+    }
+}
+
+/// Announces the cities to Home Assistant, then publishes their forecasts
+/// every `interval`.
+pub fn run(
+    client: &CptecClient,
+    cities: &[City],
+    topics: &Topics,
+    mut publisher: Publisher,
+    interval: Duration,
+    count: Option<usize>,
+) -> Result<()> {
+    for city in cities {
+        for message in topics.discovery(city) {
+            publisher.publish(&message)?;
+        }
+    }
+
+    for round in 0.. {
+        if count.is_some_and(|count| round >= count) {
+            break;
+        }
+
+        if round > 0 {
+            thread::sleep(interval);
+        }
+
+        for city in cities {
+            if let Some(message) = topics.state_message(client, city) {
+                publisher.publish(&message)?;
+            }
+        }
+    }
+
+    publisher.disconnect()
+}
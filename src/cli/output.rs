@@ -0,0 +1,179 @@
+use anyhow::Result;
+use chrono::Local;
+use meteo::{ArchiveEntry, City, Forecast};
+use serde::Serialize;
+
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ArgEnum)]
+pub enum Format {
+    Table,
+    Json,
+    Csv,
+}
+
+fn print_json(value: &impl Serialize, out: &mut impl Write) -> Result<()> {
+    serde_json::to_writer_pretty(&mut *out, value)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+fn print_csv<T: Serialize>(records: &[T], out: &mut impl Write) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+    for record in records {
+        writer.serialize(record)?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+fn optional<T: ToString>(value: Option<T>, unit: &str) -> String {
+    value
+        .map(|value| format!("{}{}", value.to_string(), unit))
+        .unwrap_or_else(|| "-".to_owned())
+}
+
+pub fn print_table(headers: &[&str], rows: &[Vec<String>], out: &mut impl Write) -> Result<()> {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut print_row = |cells: &[&str]| -> Result<()> {
+        let line: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| {
+                let padding = width - cell.chars().count();
+                format!("{}{}", cell, " ".repeat(padding))
+            })
+            .collect();
+
+        writeln!(out, "{}", line.join("  ").trim_end())?;
+        Ok(())
+    };
+
+    print_row(headers)?;
+    for row in rows {
+        print_row(&row.iter().map(String::as_str).collect::<Vec<_>>())?;
+    }
+
+    Ok(())
+}
+
+pub fn print_cities(cities: &[City], format: Format, out: &mut impl Write) -> Result<()> {
+    match format {
+        Format::Json => return print_json(&cities, out),
+        Format::Csv => return print_csv(cities, out),
+        Format::Table => {}
+    }
+
+    let rows: Vec<Vec<String>> = cities
+        .iter()
+        .enumerate()
+        .map(|(i, city)| {
+            vec![
+                i.to_string(),
+                city.name.clone(),
+                city.uf.clone(),
+                city.id.to_string(),
+            ]
+        })
+        .collect();
+
+    print_table(&["#", "Cidade", "UF", "Id"], &rows, out)
+}
+
+pub fn print_forecast(forecast: &Forecast, format: Format, out: &mut impl Write) -> Result<()> {
+    match format {
+        Format::Json => return print_json(forecast, out),
+        Format::Csv => return print_csv(&forecast.days, out),
+        Format::Table => {}
+    }
+
+    let headers = [
+        "Data",
+        "Condição",
+        "Mín",
+        "Máx",
+        "Chuva",
+        "Umidade",
+        "Vento",
+        "UV",
+    ];
+
+    let rows: Vec<Vec<String>> = forecast
+        .days
+        .iter()
+        .map(|day| {
+            let humidity = match (day.min_humidity, day.max_humidity) {
+                (Some(min), Some(max)) if min != max => format!("{}-{}%", min, max),
+                (humidity, _) => optional(humidity, "%"),
+            };
+
+            vec![
+                day.date.clone(),
+                day.condition.clone(),
+                optional(day.min_temperature, "°C"),
+                optional(day.max_temperature, "°C"),
+                optional(day.rain_probability, "%"),
+                humidity,
+                optional(day.wind.as_deref(), ""),
+                optional(day.uv_index, ""),
+            ]
+        })
+        .collect();
+
+    print_table(&headers, &rows, out)
+}
+
+#[derive(Serialize)]
+struct HistoryRecord<'a> {
+    id: usize,
+    city: &'a str,
+    uf: &'a str,
+    fetched_at: String,
+    url: &'a str,
+    sha256: &'a str,
+}
+
+pub fn print_history(entries: &[ArchiveEntry], format: Format, out: &mut impl Write) -> Result<()> {
+    let records: Vec<HistoryRecord> = entries
+        .iter()
+        .map(|entry| HistoryRecord {
+            id: entry.id,
+            city: &entry.city.name,
+            uf: &entry.city.uf,
+            fetched_at: entry.fetched_at.to_rfc3339(),
+            url: &entry.url,
+            sha256: &entry.sha256,
+        })
+        .collect();
+
+    match format {
+        Format::Json => return print_json(&records, out),
+        Format::Csv => return print_csv(&records, out),
+        Format::Table => {}
+    }
+
+    let rows: Vec<Vec<String>> = entries
+        .iter()
+        .map(|entry| {
+            vec![
+                entry.id.to_string(),
+                entry
+                    .fetched_at
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string(),
+                entry.city.label(),
+                entry.sha256.get(..12).unwrap_or(&entry.sha256).to_owned(),
+            ]
+        })
+        .collect();
+
+    print_table(&["Id", "Data", "Cidade", "SHA-256"], &rows, out)
+}
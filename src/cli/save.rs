@@ -0,0 +1,176 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local};
+use meteo::City;
+
+use super::template;
+
+use std::{
+    env::temp_dir,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+#[derive(clap::Args)]
+pub struct Overwrite {
+    /// Replace existing files
+    #[clap(long, conflicts_with = "no-clobber")]
+    force: bool,
+
+    /// Leave existing files alone instead of failing
+    #[clap(long)]
+    no_clobber: bool,
+}
+
+impl Overwrite {
+    pub const FORCE: Overwrite = Overwrite {
+        force: true,
+        no_clobber: false,
+    };
+}
+
+/// Renders the output template, placing the default file name inside the
+/// target when it is a directory.
+pub fn output_path(target: &str, city: &City, time: &DateTime<Local>) -> Result<PathBuf> {
+    let rendered = PathBuf::from(template::render(target, city, time, "png")?);
+
+    if target.ends_with('/') || target.ends_with(std::path::MAIN_SEPARATOR) || rendered.is_dir() {
+        let file_name = template::render(template::DEFAULT_TEMPLATE, city, time, "png")?;
+        return Ok(rendered.join(file_name));
+    }
+
+    Ok(rendered)
+}
+
+/// The `-o` value that sends the image to stdout instead of a file.
+pub const STDOUT: &str = "-";
+
+pub fn write_stdout(bytes: &[u8]) -> Result<()> {
+    let mut out = io::stdout().lock();
+    out.write_all(bytes)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Writes to a temporary file next to the target and moves it into place,
+/// so readers never see a partially written image. Unless forced, the move is
+/// a hard link that fails if the target appeared meanwhile, so an existing
+/// file is never replaced. Returns whether the file was written.
+pub fn save_meteogram(bytes: &[u8], path: &Path, overwrite: &Overwrite) -> Result<bool> {
+    let exists = || -> Result<bool> {
+        if overwrite.no_clobber {
+            return Ok(false);
+        }
+
+        bail!(
+            "{} already exists, use --force to replace it or --no-clobber to keep it",
+            path.display()
+        );
+    };
+
+    if path.exists() && !overwrite.force {
+        return exists();
+    }
+
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).with_context(|| format!("Could not create {}", dir.display()))?;
+    }
+
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("{} is not a file path", path.display()))?;
+    // Unique within the process too, since batch workers may save the same
+    // city at the same time.
+    static SAVES: AtomicUsize = AtomicUsize::new(0);
+    let temp_path = path.with_file_name(format!(
+        ".{}.{}.{}.tmp",
+        file_name.to_string_lossy(),
+        process::id(),
+        SAVES.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let write = || -> io::Result<()> {
+        let mut file = File::create(&temp_path)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+
+        if overwrite.force {
+            fs::rename(&temp_path, path)
+        } else {
+            fs::hard_link(&temp_path, path)
+        }
+    };
+
+    let written = write();
+    let _ = fs::remove_file(&temp_path);
+    match written {
+        Ok(()) => Ok(true),
+        Err(error) if error.kind() == io::ErrorKind::AlreadyExists => exists(),
+        Err(error) => Err(error).with_context(|| format!("Could not write {}", path.display())),
+    }
+}
+
+/// The file `show_meteogram` writes for the external viewer.
+pub fn viewer_path() -> PathBuf {
+    temp_dir().join("meteo.png")
+}
+
+pub fn show_meteogram(bytes: &[u8]) -> Result<()> {
+    let temp_path = viewer_path();
+    save_meteogram(bytes, &temp_path, &Overwrite::FORCE)?;
+    open::that(&temp_path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+
+    fn temp_target(name: &str) -> PathBuf {
+        let dir = temp_dir().join(format!("meteo-save-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("meteo.png")
+    }
+
+    #[test]
+    fn concurrent_no_clobber_saves_write_once() {
+        let path = temp_target("no-clobber");
+        let overwrite = Overwrite {
+            force: false,
+            no_clobber: true,
+        };
+
+        let written: Vec<bool> = thread::scope(|scope| {
+            let saves: Vec<_> = (0..8u8)
+                .map(|i| {
+                    let (path, overwrite) = (&path, &overwrite);
+                    scope.spawn(move || save_meteogram(&[i; 64], path, overwrite).unwrap())
+                })
+                .collect();
+            saves.into_iter().map(|save| save.join().unwrap()).collect()
+        });
+
+        assert_eq!(written.iter().filter(|&&written| written).count(), 1);
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn existing_file_is_an_error_unless_forced() {
+        let path = temp_target("force");
+
+        assert!(save_meteogram(b"first", &path, &Overwrite::FORCE).unwrap());
+        let overwrite = Overwrite {
+            force: false,
+            no_clobber: false,
+        };
+        let error = save_meteogram(b"second", &path, &overwrite).unwrap_err();
+        assert!(error.to_string().contains("already exists"), "{}", error);
+
+        assert!(save_meteogram(b"third", &path, &Overwrite::FORCE).unwrap());
+        assert_eq!(fs::read(&path).unwrap(), b"third");
+    }
+}
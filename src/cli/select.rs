@@ -0,0 +1,210 @@
+use anyhow::{bail, Context, Result};
+use meteo::{normalize, City};
+
+use super::Exit;
+
+use std::io::{stderr, stdin, BufRead, IsTerminal, Write};
+
+#[derive(clap::Args)]
+pub struct UfFilter {
+    /// Only consider cities of this state, e.g. SC (repeatable)
+    #[clap(long = "uf", value_name = "UF")]
+    ufs: Vec<String>,
+}
+
+impl UfFilter {
+    pub fn apply(&self, mut cities: Vec<City>) -> Vec<City> {
+        if !self.ufs.is_empty() {
+            cities.retain(|city| self.ufs.iter().any(|uf| uf.eq_ignore_ascii_case(&city.uf)));
+        }
+
+        cities
+    }
+}
+
+#[derive(clap::Args)]
+pub struct Selection {
+    #[clap(flatten)]
+    pub uf: UfFilter,
+
+    /// Pick the city at this position of the list, without prompting
+    #[clap(long, conflicts_with_all = &["first", "id"])]
+    index: Option<usize>,
+
+    /// Pick the first city of the list, without prompting
+    #[clap(long, conflicts_with = "id")]
+    first: bool,
+
+    /// Only consider cities whose name matches the query, ignoring case and accents
+    #[clap(long)]
+    exact: bool,
+
+    /// Pick the city with this CPTEC id, without prompting
+    #[clap(long)]
+    id: Option<u32>,
+}
+
+fn matches_exactly(city: &City, query: &str) -> bool {
+    let query = normalize(query);
+    normalize(&city.label()) == query || normalize(&city.name) == query
+}
+
+pub fn select_city<'a>(cities: &'a [City], query: &str, selection: &Selection) -> Result<&'a City> {
+    let candidates: Vec<&City> = cities
+        .iter()
+        .filter(|city| !selection.exact || matches_exactly(city, query))
+        .collect();
+
+    if let Some(id) = &selection.id {
+        return candidates
+            .into_iter()
+            .find(|city| city.id == *id)
+            .with_context(|| format!("No city with id {} matches \"{}\"", id, query));
+    }
+
+    if candidates.is_empty() {
+        return Err(Exit::NoCitiesFound.into());
+    }
+
+    if let Some(index) = selection.index {
+        return candidates.get(index).copied().with_context(|| {
+            format!(
+                "Index {} is out of range for {} cities",
+                index,
+                candidates.len()
+            )
+        });
+    }
+
+    if selection.first || candidates.len() == 1 {
+        return Ok(candidates[0]);
+    }
+
+    if !stdin().is_terminal() {
+        bail!(
+            "{} cities match \"{}\" and stdin is not a terminal; \
+             use --index, --first, --exact or --id to pick one",
+            candidates.len(),
+            query
+        );
+    }
+
+    select_city_prompt(&candidates, &mut stdin().lock(), &mut stderr())
+}
+
+fn select_city_prompt<'a>(
+    cities: &[&'a City],
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+) -> Result<&'a City> {
+    let width = cities
+        .iter()
+        .map(|city| city.name.chars().count())
+        .max()
+        .unwrap_or_default();
+
+    for (i, city) in cities.iter().enumerate() {
+        let padding = " ".repeat(width - city.name.chars().count());
+        writeln!(out, "[{:2}] {}{}  {}", i, city.name, padding, city.uf)?;
+    }
+
+    writeln!(
+        out,
+        "\nDigite o número da cidade desejada (ou q para sair): "
+    )?;
+
+    loop {
+        let mut input_buffer = String::new();
+        if input.read_line(&mut input_buffer)? == 0 {
+            return Err(Exit::Quit.into());
+        }
+
+        let input = input_buffer.trim();
+        if input.eq_ignore_ascii_case("q") {
+            return Err(Exit::Quit.into());
+        }
+
+        match input.parse::<usize>().ok().and_then(|i| cities.get(i)) {
+            Some(city) => return Ok(city),
+            None => writeln!(
+                out,
+                "Opção inválida, digite um número entre 0 e {} (ou q para sair): ",
+                cities.len() - 1
+            )?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use meteo::RawCity;
+
+    use std::convert::TryFrom;
+
+    fn cities() -> Vec<City> {
+        [
+            ("5012", "São José/SC"),
+            ("5013", "São José do Rio Preto/SP"),
+        ]
+        .iter()
+        .map(|(id, label)| {
+            City::try_from(RawCity {
+                id: id.to_string(),
+                label: label.to_string(),
+                value: String::new(),
+                custom: "sc/sao-jose".to_owned(),
+            })
+            .unwrap()
+        })
+        .collect()
+    }
+
+    fn prompt(input: &str) -> (Result<u32>, String) {
+        let cities = cities();
+        let candidates: Vec<&City> = cities.iter().collect();
+        let mut out = Vec::new();
+
+        let result = select_city_prompt(&candidates, &mut input.as_bytes(), &mut out);
+        (result.map(|city| city.id), String::from_utf8(out).unwrap())
+    }
+
+    fn is_quit(result: Result<u32>) -> bool {
+        matches!(result.unwrap_err().downcast_ref(), Some(Exit::Quit))
+    }
+
+    #[test]
+    fn lists_aligned_cities_and_picks_by_number() {
+        let (result, out) = prompt("1\n");
+
+        assert_eq!(result.unwrap(), 5013);
+        assert!(out.starts_with("[ 0] São José               SC\n[ 1] São José do Rio Preto  SP\n"));
+    }
+
+    #[test]
+    fn asks_again_on_invalid_or_out_of_range_input() {
+        let (result, out) = prompt("abc\n7\n\n 0 \n");
+
+        assert_eq!(result.unwrap(), 5012);
+        let retries = "Opção inválida, digite um número entre 0 e 1 (ou q para sair): \n";
+        assert_eq!(out.matches(retries).count(), 3);
+    }
+
+    #[test]
+    fn q_quits() {
+        let (result, out) = prompt("5\nQ\n1\n");
+
+        assert!(is_quit(result));
+        assert_eq!(out.matches("Opção inválida").count(), 1);
+    }
+
+    #[test]
+    fn end_of_input_quits() {
+        let (result, _) = prompt("");
+        assert!(is_quit(result));
+
+        let (result, _) = prompt("9\n");
+        assert!(is_quit(result));
+    }
+}
@@ -0,0 +1,297 @@
+use anyhow::{anyhow, Result};
+use meteo::{normalize, Archive, City, CptecClient};
+use reqwest::Url;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use super::{history::fetch_meteogram, metrics::Metrics};
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How many requests are answered at the same time.
+const WORKERS: usize = 4;
+
+const SEARCH_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const FORECAST_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// How many replies are kept in memory, the oldest being dropped first.
+const MAX_CACHED_REPLIES: usize = 1024;
+
+#[derive(Clone)]
+struct Reply {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+    /// How long clients may cache the reply; errors are never cached.
+    ttl: Option<Duration>,
+}
+
+impl Reply {
+    fn ok(content_type: &'static str, body: Vec<u8>, ttl: Duration) -> Self {
+        Self {
+            status: 200,
+            content_type,
+            body,
+            ttl: Some(ttl),
+        }
+    }
+
+    fn json(value: &impl serde::Serialize, ttl: Duration) -> Self {
+        match serde_json::to_vec(value) {
+            Ok(body) => Self::ok("application/json; charset=utf-8", body, ttl),
+            Err(error) => Self::error(500, &error.to_string()),
+        }
+    }
+
+    /// A reply that is always built anew, like the metrics.
+    fn live(content_type: &'static str, body: Vec<u8>) -> Self {
+        Self {
+            status: 200,
+            content_type,
+            body,
+            ttl: None,
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: format!("{}\n", message).into_bytes(),
+            ttl: None,
+        }
+    }
+
+    fn into_response(self, age: Duration) -> Response<std::io::Cursor<Vec<u8>>> {
+        let header = |name: &str, value: &str| {
+            Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("valid header")
+        };
+
+        let cache_control = match self.ttl {
+            Some(ttl) => format!("public, max-age={}", ttl.saturating_sub(age).as_secs()),
+            None => "no-store".to_owned(),
+        };
+
+        Response::from_data(self.body)
+            .with_status_code(self.status)
+            .with_header(header("Content-Type", self.content_type))
+            .with_header(header("Cache-Control", &cache_control))
+    }
+}
+
+struct Cached {
+    reply: Reply,
+    fetched_at: Instant,
+}
+
+/// Requests that get the same reply share a key: only the search query is
+/// kept, normalized, so "/cities?q=São+José" and "/cities/?q=sao%20jose&x=1"
+/// are cached once.
+fn cache_key(url: &Url) -> String {
+    let path = url.path().trim_matches('/');
+
+    match url.query_pairs().find(|(name, _)| name == "q") {
+        Some((_, query)) if path == "cities" => format!("{}?q={}", path, normalize(&query)),
+        _ => path.to_owned(),
+    }
+}
+
+/// Answers requests from the CPTEC client, keeping successful replies in
+/// memory for as long as they tell clients to cache them.
+pub struct MeteoServer<'a> {
+    client: &'a CptecClient,
+    archive: Option<&'a Archive>,
+    /// Cities by id, from the index and from previous searches, since the
+    /// forecast URL of a city cannot be built from its id alone.
+    cities: Mutex<HashMap<u32, City>>,
+    replies: Mutex<HashMap<String, Cached>>,
+    /// Cities whose forecast is refreshed on every scrape of `/metrics`.
+    watched: Vec<City>,
+    metrics: Metrics,
+}
+
+impl<'a> MeteoServer<'a> {
+    pub fn new(client: &'a CptecClient, archive: Option<&'a Archive>, cities: &[City]) -> Self {
+        Self {
+            client,
+            archive,
+            cities: Mutex::new(cities.iter().map(|city| (city.id, city.clone())).collect()),
+            replies: Mutex::new(HashMap::new()),
+            watched: Vec::new(),
+            metrics: Metrics::default(),
+        }
+    }
+
+    /// Watched cities can be asked for by id without being searched first.
+    pub fn with_watched(self, watched: Vec<City>) -> Self {
+        self.cities
+            .lock()
+            .unwrap()
+            .extend(watched.iter().map(|city| (city.id, city.clone())));
+
+        Self { watched, ..self }
+    }
+
+    pub fn run(&self, bind: &str) -> Result<()> {
+        let server =
+            Server::http(bind).map_err(|error| anyhow!("Could not bind {}: {}", bind, error))?;
+
+        if let Some(addr) = server.server_addr().to_ip() {
+            eprintln!("Servindo em http://{}", addr);
+        }
+
+        thread::scope(|scope| {
+            for _ in 0..WORKERS {
+                scope.spawn(|| {
+                    while let Ok(request) = server.recv() {
+                        self.respond(request);
+                    }
+                });
+            }
+        });
+
+        Ok(())
+    }
+
+    fn respond(&self, request: Request) {
+        let (reply, age) = if *request.method() == Method::Get {
+            self.cached(request.url())
+        } else {
+            (Reply::error(405, "Only GET is supported"), Duration::ZERO)
+        };
+
+        // The client may have hung up already, nothing to do about it.
+        let _ = request.respond(reply.into_response(age));
+    }
+
+    fn cached(&self, target: &str) -> (Reply, Duration) {
+        let url = match Url::parse("http://localhost").and_then(|base| base.join(target)) {
+            Ok(url) => url,
+            Err(_) => return (Reply::error(400, "Invalid URL"), Duration::ZERO),
+        };
+
+        let key = cache_key(&url);
+
+        if let Some(cached) = self.replies.lock().unwrap().get(&key) {
+            let age = cached.fetched_at.elapsed();
+            if cached.reply.ttl.is_some_and(|ttl| age < ttl) {
+                return (cached.reply.clone(), age);
+            }
+        }
+
+        let reply = self.route(&url);
+
+        if reply.ttl.is_some() {
+            let mut replies = self.replies.lock().unwrap();
+            replies.retain(|_, cached| {
+                cached
+                    .reply
+                    .ttl
+                    .is_some_and(|ttl| cached.fetched_at.elapsed() < ttl)
+            });
+            if replies.len() >= MAX_CACHED_REPLIES {
+                let oldest = replies
+                    .iter()
+                    .min_by_key(|(_, cached)| cached.fetched_at)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    replies.remove(&oldest);
+                }
+            }
+            replies.insert(
+                key,
+                Cached {
+                    reply: reply.clone(),
+                    fetched_at: Instant::now(),
+                },
+            );
+        }
+
+        (reply, Duration::ZERO)
+    }
+
+    fn route(&self, url: &Url) -> Reply {
+        let segments: Vec<&str> = url.path().trim_matches('/').split('/').collect();
+
+        match segments.as_slice() {
+            ["metrics"] => self.metrics(),
+            ["cities"] => {
+                let query = url.query_pairs().find(|(name, _)| name == "q");
+                match query {
+                    Some((_, query)) if !query.trim().is_empty() => self.search(&query),
+                    _ => Reply::error(400, "Missing query, use /cities?q=<name>"),
+                }
+            }
+            ["city", id, resource] => {
+                let city = match id.parse().ok().and_then(|id| self.city(id)) {
+                    Some(city) => city,
+                    None => return Reply::error(404, "Unknown city, find it with /cities first"),
+                };
+
+                match *resource {
+                    "meteogram.png" => self.meteogram(&city),
+                    "forecast.json" => self.forecast(&city),
+                    _ => Reply::error(404, "Not found"),
+                }
+            }
+            _ => Reply::error(404, "Not found"),
+        }
+    }
+
+    fn city(&self, id: u32) -> Option<City> {
+        self.cities.lock().unwrap().get(&id).cloned()
+    }
+
+    fn search(&self, query: &str) -> Reply {
+        let cities = match self.client.search(query) {
+            Ok(cities) => cities,
+            Err(error) => return Reply::error(502, &format!("{:#}", error)),
+        };
+
+        let mut known = self.cities.lock().unwrap();
+        for city in &cities {
+            known.insert(city.id, city.clone());
+        }
+
+        Reply::json(&cities, SEARCH_TTL)
+    }
+
+    fn meteogram(&self, city: &City) -> Reply {
+        match fetch_meteogram(self.client, self.archive, city) {
+            Ok(meteogram) => Reply::ok("image/png", meteogram, FORECAST_TTL),
+            Err(error) => {
+                self.metrics.record_error(city, &error);
+                Reply::error(502, &format!("{:#}", error))
+            }
+        }
+    }
+
+    fn forecast(&self, city: &City) -> Reply {
+        match self.client.forecast(city) {
+            Ok(forecast) => {
+                self.metrics.record_forecast(city, &forecast);
+                Reply::json(&forecast, FORECAST_TTL)
+            }
+            Err(error) => {
+                self.metrics.record_error(city, &error);
+                Reply::error(502, &format!("{:#}", error))
+            }
+        }
+    }
+
+    fn metrics(&self) -> Reply {
+        for city in &self.watched {
+            self.metrics.collect(self.client, city);
+        }
+
+        let mut body = Vec::new();
+        match self.metrics.render(&mut body) {
+            Ok(()) => Reply::live("text/plain; version=0.0.4; charset=utf-8", body),
+            Err(error) => Reply::error(500, &error.to_string()),
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Local};
+use meteo::{normalize, City};
+
+pub const DEFAULT_TEMPLATE: &str = "{city}-{uf}-{date}.{ext}";
+
+/// Fills in `{city}`, `{uf}`, `{id}`, `{date}`, `{time}` and `{ext}`. City
+/// names are turned into slugs so they are safe to use in file names.
+pub fn render(template: &str, city: &City, time: &DateTime<Local>, ext: &str) -> Result<String> {
+    let mut rendered = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => bail!("Unclosed placeholder in {:?}", template),
+        };
+
+        match &rest[start + 1..end] {
+            "city" => rendered.push_str(&slug(&city.name)),
+            "uf" => rendered.push_str(&city.uf),
+            "id" => rendered.push_str(&city.id.to_string()),
+            "date" => rendered.push_str(&time.format("%Y-%m-%d").to_string()),
+            "time" => rendered.push_str(&time.format("%H%M%S").to_string()),
+            "ext" => rendered.push_str(ext),
+            other => bail!("Unknown placeholder {{{}}} in {:?}", other, template),
+        }
+
+        rest = &rest[end + 1..];
+    }
+
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+fn slug(name: &str) -> String {
+    normalize(name)
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect()
+}
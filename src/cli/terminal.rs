@@ -0,0 +1,219 @@
+use anyhow::{Context, Result};
+use image::{imageops::FilterType, ImageFormat, RgbImage};
+
+use std::{
+    env,
+    io::{self, Write},
+};
+
+const DEFAULT_COLUMNS: u32 = 80;
+
+/// Rough width of a terminal cell in pixels, used to size sixel output.
+const CELL_WIDTH: u32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ArgEnum)]
+pub enum Protocol {
+    Auto,
+    Kitty,
+    Iterm2,
+    Sixel,
+    Blocks,
+}
+
+impl Protocol {
+    fn detect() -> Self {
+        let var = |name| env::var(name).unwrap_or_default();
+        let term = var("TERM");
+        let term_program = var("TERM_PROGRAM");
+
+        if env::var_os("KITTY_WINDOW_ID").is_some() || term == "xterm-kitty" {
+            Protocol::Kitty
+        } else if term_program == "iTerm.app"
+            || term_program == "WezTerm"
+            || var("LC_TERMINAL") == "iTerm2"
+        {
+            Protocol::Iterm2
+        } else if term.contains("sixel") || term == "foot" || term.starts_with("mlterm") {
+            Protocol::Sixel
+        } else {
+            Protocol::Blocks
+        }
+    }
+}
+
+fn terminal_columns() -> u32 {
+    terminal_size::terminal_size()
+        .map(|(width, _)| u32::from(width.0))
+        .unwrap_or(DEFAULT_COLUMNS)
+}
+
+pub fn show_meteogram(bytes: &[u8], protocol: Protocol) -> Result<()> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    let protocol = match protocol {
+        Protocol::Auto => Protocol::detect(),
+        protocol => protocol,
+    };
+
+    match protocol {
+        Protocol::Kitty => kitty(bytes, terminal_columns(), &mut out)?,
+        Protocol::Iterm2 => iterm2(bytes, terminal_columns(), &mut out)?,
+        Protocol::Sixel => sixel(&decode(bytes, terminal_columns() * CELL_WIDTH)?, &mut out)?,
+        Protocol::Blocks | Protocol::Auto => blocks(&decode(bytes, terminal_columns())?, &mut out)?,
+    }
+
+    writeln!(out)?;
+    Ok(out.flush()?)
+}
+
+/// Decodes the PNG, shrinking it to at most `max_width` pixels wide.
+fn decode(bytes: &[u8], max_width: u32) -> Result<RgbImage> {
+    let image = image::load_from_memory_with_format(bytes, ImageFormat::Png)
+        .context("Could not decode meteogram")?
+        .to_rgb8();
+
+    if image.width() <= max_width {
+        return Ok(image);
+    }
+
+    let height = image.height() * max_width / image.width();
+    Ok(image::imageops::resize(
+        &image,
+        max_width,
+        height.max(1),
+        FilterType::Triangle,
+    ))
+}
+
+fn kitty(png: &[u8], columns: u32, out: &mut impl Write) -> Result<()> {
+    let encoded = base64::encode(png);
+    let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(4096).collect();
+
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+
+        if i == 0 {
+            write!(out, "\x1b_Gf=100,a=T,c={},m={};", columns, more)?;
+        } else {
+            write!(out, "\x1b_Gm={};", more)?;
+        }
+
+        out.write_all(chunk)?;
+        write!(out, "\x1b\\")?;
+    }
+
+    Ok(())
+}
+
+fn iterm2(png: &[u8], columns: u32, out: &mut impl Write) -> Result<()> {
+    write!(
+        out,
+        "\x1b]1337;File=inline=1;size={};width={};preserveAspectRatio=1:{}\x07",
+        png.len(),
+        columns,
+        base64::encode(png)
+    )?;
+
+    Ok(())
+}
+
+/// Maps a color to the 6x6x6 cube used as the sixel palette.
+fn palette_index(pixel: &image::Rgb<u8>) -> usize {
+    let level = |channel: u8| (usize::from(channel) * 5 + 127) / 255;
+    let [r, g, b] = pixel.0;
+
+    level(r) * 36 + level(g) * 6 + level(b)
+}
+
+fn sixel(image: &RgbImage, out: &mut impl Write) -> Result<()> {
+    write!(out, "\x1bPq\"1;1;{};{}", image.width(), image.height())?;
+
+    for index in 0..216 {
+        let percent = |level: usize| level * 100 / 5;
+        let (r, g, b) = (index / 36, index / 6 % 6, index % 6);
+        write!(
+            out,
+            "#{};2;{};{};{}",
+            index,
+            percent(r),
+            percent(g),
+            percent(b)
+        )?;
+    }
+
+    for band in (0..image.height()).step_by(6) {
+        let rows = band..(band + 6).min(image.height());
+
+        let mut colors: Vec<usize> = rows
+            .clone()
+            .flat_map(|y| (0..image.width()).map(move |x| (x, y)))
+            .map(|(x, y)| palette_index(image.get_pixel(x, y)))
+            .collect();
+        colors.sort_unstable();
+        colors.dedup();
+
+        for color in colors {
+            write!(out, "#{}", color)?;
+
+            let sixels = (0..image.width()).map(|x| {
+                let bits = rows.clone().fold(0, |bits, y| {
+                    let hit = palette_index(image.get_pixel(x, y)) == color;
+                    bits | (u8::from(hit) << (y - band))
+                });
+
+                char::from(b'?' + bits)
+            });
+
+            write_run_length(sixels, out)?;
+            write!(out, "$")?;
+        }
+
+        write!(out, "-")?;
+    }
+
+    write!(out, "\x1b\\")?;
+    Ok(())
+}
+
+fn write_run_length(chars: impl Iterator<Item = char>, out: &mut impl Write) -> Result<()> {
+    let mut chars = chars.peekable();
+
+    while let Some(c) = chars.next() {
+        let mut count = 1;
+        while chars.peek() == Some(&c) {
+            chars.next();
+            count += 1;
+        }
+
+        if count > 3 {
+            write!(out, "!{}{}", count, c)?;
+        } else {
+            write!(out, "{}", c.to_string().repeat(count))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Draws two pixels per cell with the upper half block, using the foreground
+/// for the top pixel and the background for the bottom one.
+fn blocks(image: &RgbImage, out: &mut impl Write) -> Result<()> {
+    for y in (0..image.height()).step_by(2) {
+        for x in 0..image.width() {
+            let [r, g, b] = image.get_pixel(x, y).0;
+            write!(out, "\x1b[38;2;{};{};{}m", r, g, b)?;
+
+            if y + 1 < image.height() {
+                let [r, g, b] = image.get_pixel(x, y + 1).0;
+                write!(out, "\x1b[48;2;{};{};{}m", r, g, b)?;
+            }
+
+            write!(out, "▀")?;
+        }
+
+        writeln!(out, "\x1b[0m")?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,161 @@
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::Local;
+use meteo::{Archive, City, CptecClient};
+use sha2::{Digest, Sha256};
+
+use super::{
+    history::fetch_meteogram,
+    save::{save_meteogram, show_meteogram, viewer_path, Overwrite},
+    terminal::{self, Protocol},
+};
+
+use std::{
+    env,
+    io::{stdout, Write},
+    path::PathBuf,
+    thread,
+    time::Duration,
+};
+
+/// Shortest interval between two polls of CPTEC, which only updates its
+/// forecasts a few times a day. Tests lower it through the undocumented
+/// `METEO_MIN_INTERVAL` variable.
+const MIN_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Parses intervals like `90s`, `30m`, `1h` or `1h30m`; a bare number is
+/// taken as seconds.
+pub fn parse_interval(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    if value.is_empty() {
+        bail!("Empty interval");
+    }
+
+    let too_long = || anyhow!("Interval {:?} is too long", value);
+
+    let mut seconds: u64 = 0;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if digits == 0 {
+            bail!("Invalid interval {:?}, expected something like 30m", value);
+        }
+
+        let amount: u64 = rest[..digits].parse().map_err(|_| too_long())?;
+        rest = &rest[digits..];
+
+        let unit = rest.chars().next();
+        let multiplier = match unit {
+            None | Some('s') => 1,
+            Some('m') => 60,
+            Some('h') => 60 * 60,
+            Some('d') => 24 * 60 * 60,
+            Some(unit) => bail!("Unknown interval unit {:?} in {:?}", unit, value),
+        };
+
+        rest = unit.map_or(rest, |unit| &rest[unit.len_utf8()..]);
+        seconds = amount
+            .checked_mul(multiplier)
+            .and_then(|amount| seconds.checked_add(amount))
+            .ok_or_else(too_long)?;
+    }
+
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Parses the interval between polls of CPTEC, refusing anything shorter
+/// than a minute.
+pub fn parse_poll_interval(value: &str) -> Result<Duration> {
+    let interval = parse_interval(value)?;
+
+    let min = match env::var("METEO_MIN_INTERVAL") {
+        Ok(min) => parse_interval(&min)?,
+        Err(_) => MIN_INTERVAL,
+    };
+    if interval < min {
+        bail!("Interval {:?} is too short, use at least 1m", value);
+    }
+
+    Ok(interval)
+}
+
+/// Where each fetched meteogram is shown.
+pub enum Display {
+    Terminal(Protocol),
+    /// The file opened by the external viewer, rewritten on every change.
+    Viewer,
+    File(PathBuf),
+}
+
+impl Display {
+    fn show(&self, meteogram: &[u8], first: bool) -> Result<()> {
+        match self {
+            Display::Terminal(protocol) => {
+                if !first {
+                    // Clear the screen so the new image replaces the old one.
+                    print!("\x1b[2J\x1b[H");
+                    stdout().flush()?;
+                }
+
+                terminal::show_meteogram(meteogram, *protocol)
+            }
+            Display::Viewer if first => show_meteogram(meteogram),
+            Display::Viewer => {
+                save_meteogram(meteogram, &viewer_path(), &Overwrite::FORCE).map(drop)
+            }
+            Display::File(path) => save_meteogram(meteogram, path, &Overwrite::FORCE).map(drop),
+        }
+    }
+}
+
+/// Fetches the meteogram every `interval` and shows it again whenever its
+/// contents change. Failed fetches are reported and retried on the next
+/// round, so only `count` ends the loop.
+pub fn run(
+    client: &CptecClient,
+    archive: Option<&Archive>,
+    city: &City,
+    interval: Duration,
+    count: Option<usize>,
+    display: &Display,
+) -> Result<()> {
+    let mut last_hash = None;
+
+    for round in 0.. {
+        if count.is_some_and(|count| round >= count) {
+            break;
+        }
+
+        if round > 0 {
+            thread::sleep(interval);
+        }
+
+        let now = Local::now().format("%Y-%m-%d %H:%M");
+        let meteogram = match fetch_meteogram(client, archive, city) {
+            Ok(meteogram) => meteogram,
+            Err(error) => {
+                eprintln!("{}  erro: {:#}", now, error);
+                continue;
+            }
+        };
+
+        let hash = Sha256::digest(&meteogram);
+        if last_hash.as_ref() == Some(&hash) {
+            continue;
+        }
+
+        let first = last_hash.is_none();
+        display
+            .show(&meteogram, first)
+            .context("Could not show the meteogram")?;
+
+        if !first {
+            eprintln!("{}  meteograma de {} atualizado", now, city);
+        }
+
+        last_hash = Some(hash);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use anyhow::Result;
+use reqwest::Url;
+
+use super::{parse_forecast, Client, Crawler, Plan};
+use crate::{
+    cache::Resource,
+    city::parse_cities,
+    index::{Crawl, CrawlOptions},
+    rank::rank_cities,
+    City, Forecast,
+};
+
+pub type AsyncCptecClient = Client<reqwest::Client>;
+
+impl AsyncCptecClient {
+    async fn get(&self, url: Url, resource: Resource) -> Result<Vec<u8>> {
+        let pending = match self.plan(url, resource) {
+            Plan::Cached(body) => return Ok(body),
+            Plan::Send(pending) => pending,
+        };
+
+        let request = self
+            .http
+            .get(pending.url.clone())
+            .headers(pending.headers());
+        let response = request.send().await?.error_for_status()?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.bytes().await?.to_vec();
+
+        self.complete(pending, status, &headers, body)
+    }
+
+    async fn autocomplete(&self, query: &str) -> Result<Vec<City>> {
+        let url = self.base_url.autocomplete(query)?;
+        let json = self.get(url, Resource::Search).await?;
+
+        parse_cities(&json)
+    }
+
+    pub async fn search(&self, query: &str) -> Result<Vec<City>> {
+        if let Some(index) = &self.index {
+            return Ok(index.search(query));
+        }
+
+        let mut cities = self.autocomplete(query).await?;
+        rank_cities(query, &mut cities);
+        Ok(cities)
+    }
+
+    /// Queries the autocomplete endpoint for every prefix described by the
+    /// options, reporting each prefix and its number of results. Prefixes
+    /// that still fail after `options.retries` retries are skipped, and
+    /// returned along with the cities the other prefixes found and the
+    /// prefixes still cut off at `options.max_prefix_len`.
+    pub async fn crawl_index(
+        &self,
+        options: &CrawlOptions,
+        progress: impl FnMut(&str, usize),
+    ) -> Crawl {
+        let mut crawler = Crawler::new(options, progress);
+
+        while let Some(prefix) = crawler.next_prefix() {
+            if let Some(delay) = crawler.delay() {
+                tokio::time::sleep(delay).await;
+            }
+            crawler.record(self.autocomplete(&prefix).await);
+        }
+
+        crawler.finish()
+    }
+
+    pub async fn forecast_page(&self, city: &City) -> Result<String> {
+        let url = self.forecast_url(city)?;
+        let page = self.get(url, Resource::ForecastPage).await?;
+
+        Ok(String::from_utf8_lossy(&page).into_owned())
+    }
+
+    pub async fn forecast(&self, city: &City) -> Result<Forecast> {
+        let page_contents = self.forecast_page(city).await?;
+        parse_forecast(&page_contents)
+    }
+
+    pub async fn meteogram(&self, city: &City) -> Result<Vec<u8>> {
+        let (_, meteogram) = self.meteogram_with_url(city).await?;
+        Ok(meteogram)
+    }
+
+    /// Where the meteogram image of a city is, as linked from its forecast page.
+    pub async fn meteogram_url(&self, city: &City) -> Result<Url> {
+        let page_contents = self.forecast_page(city).await?;
+        self.meteogram_url_from_page(&page_contents)
+    }
+
+    /// The meteogram along with the URL of the image it was downloaded from.
+    pub async fn meteogram_with_url(&self, city: &City) -> Result<(Url, Vec<u8>)> {
+        let url = self.meteogram_url(city).await?;
+        let meteogram = self.get(url.clone(), Resource::Meteogram).await?;
+
+        Ok((url, meteogram))
+    }
+}
@@ -0,0 +1,279 @@
+mod asynchronous;
+#[cfg(feature = "blocking")]
+mod blocking;
+
+pub use asynchronous::AsyncCptecClient;
+#[cfg(feature = "blocking")]
+pub use blocking::CptecClient;
+
+use anyhow::{Context, Result};
+use reqwest::{header::HeaderMap, StatusCode, Url};
+
+use crate::{
+    cache::{Cache, Lookup, Resource, Stale},
+    index::{CityIndex, Crawl, CrawlOptions},
+    scrape::{scrape_forecast, scrape_meteogram_url, ScrapeError},
+    City, Forecast,
+};
+
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+
+pub const DEFAULT_BASE_URL: &str = "https://tempo.cptec.inpe.br";
+
+#[derive(Debug, Clone)]
+struct BaseUrl(Url);
+
+impl Default for BaseUrl {
+    fn default() -> Self {
+        Self(Url::parse(DEFAULT_BASE_URL).expect("default base URL is valid"))
+    }
+}
+
+impl BaseUrl {
+    fn parse(base_url: &str) -> Result<Self> {
+        let mut url =
+            Url::parse(base_url).with_context(|| format!("Invalid base URL: {}", base_url))?;
+
+        // Without a trailing slash, joining would replace the last path segment.
+        if !url.path().ends_with('/') {
+            let path = format!("{}/", url.path());
+            url.set_path(&path);
+        }
+
+        Ok(Self(url))
+    }
+
+    fn resolve(&self, path: &str) -> Result<Url> {
+        self.0
+            .join(path)
+            .with_context(|| format!("Could not resolve {} against {}", path, self.0))
+    }
+
+    fn autocomplete(&self, query: &str) -> Result<Url> {
+        let mut url = self.resolve("autocomplete")?;
+        url.query_pairs_mut().append_pair("term", query);
+        Ok(url)
+    }
+
+    fn forecast(&self, city: &City) -> Result<Url> {
+        self.resolve(&city.custom())
+    }
+
+    /// Pages link the image by absolute URL on CPTEC itself, so when the
+    /// base URL is a mirror, a `src` on CPTEC keeps only its path and query,
+    /// re-based onto the mirror. Images on any other host are left alone.
+    fn meteogram(&self, page_contents: &str) -> Result<Url> {
+        let src = scrape_meteogram_url(page_contents).ok_or(ScrapeError::MissingMeteogram)?;
+        let url = self.resolve(&src)?;
+
+        let cptec = BaseUrl::default().0.origin();
+        if url.origin() != cptec || self.0.origin() == cptec {
+            return Ok(url);
+        }
+
+        let path = url.path().trim_start_matches('/');
+        let mut rebased = self.resolve(path)?;
+        rebased.set_query(url.query());
+        Ok(rebased)
+    }
+}
+
+fn parse_forecast(page_contents: &str) -> Result<Forecast> {
+    let forecast = scrape_forecast(page_contents);
+
+    if forecast.days.is_empty() {
+        return Err(ScrapeError::MissingForecast.into());
+    }
+
+    Ok(forecast)
+}
+
+/// A CPTEC client, generic over the HTTP client that sends its requests:
+/// `CptecClient` blocks and `AsyncCptecClient` returns futures. Everything
+/// but sending lives here, shared by both.
+#[derive(Debug, Clone, Default)]
+pub struct Client<H> {
+    http: H,
+    base_url: BaseUrl,
+    cache: Option<Cache>,
+    index: Option<Arc<CityIndex>>,
+}
+
+/// How a GET goes: answered from the cache, or sent to the server.
+enum Plan {
+    Cached(Vec<u8>),
+    Send(Pending),
+}
+
+/// A GET to send, along with the stale cache entry it revalidates.
+struct Pending {
+    url: Url,
+    stale: Option<Stale>,
+}
+
+impl Pending {
+    fn headers(&self) -> HeaderMap {
+        self.stale
+            .as_ref()
+            .map(Stale::conditional_headers)
+            .unwrap_or_default()
+    }
+}
+
+impl<H: Default> Client<H> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_base_url(base_url: &str) -> Result<Self> {
+        Ok(Self {
+            base_url: BaseUrl::parse(base_url)?,
+            ..Self::default()
+        })
+    }
+}
+
+impl<H> Client<H> {
+    pub fn with_cache(self, cache: Cache) -> Self {
+        Self {
+            cache: Some(cache),
+            ..self
+        }
+    }
+
+    /// Answers searches from the index instead of the autocomplete endpoint.
+    pub fn with_index(self, index: CityIndex) -> Self {
+        Self {
+            index: Some(Arc::new(index)),
+            ..self
+        }
+    }
+
+    pub fn base_url(&self) -> &Url {
+        &self.base_url.0
+    }
+
+    pub fn forecast_url(&self, city: &City) -> Result<Url> {
+        self.base_url.forecast(city)
+    }
+
+    /// Where the meteogram is, as linked from an already fetched forecast
+    /// page, so one fetch can serve both the forecast and the meteogram URL.
+    pub fn meteogram_url_from_page(&self, page_contents: &str) -> Result<Url> {
+        self.base_url.meteogram(page_contents)
+    }
+
+    fn plan(&self, url: Url, resource: Resource) -> Plan {
+        let stale = match self
+            .cache
+            .as_ref()
+            .map(|cache| cache.lookup(&url, resource))
+        {
+            Some(Lookup::Fresh(body)) => return Plan::Cached(body),
+            Some(Lookup::Stale(stale)) => Some(stale),
+            Some(Lookup::Miss) | None => None,
+        };
+
+        Plan::Send(Pending { url, stale })
+    }
+
+    /// Stores the answer to a GET, or marks the stale entry it revalidated
+    /// as fresh again if the server answered 304.
+    fn complete(
+        &self,
+        pending: Pending,
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        let cache = match &self.cache {
+            Some(cache) => cache,
+            None => return Ok(body),
+        };
+
+        match pending.stale {
+            Some(stale) if status == StatusCode::NOT_MODIFIED => cache.revalidated(stale),
+            _ => {
+                cache.store(&pending.url, headers, &body)?;
+                Ok(body)
+            }
+        }
+    }
+}
+
+/// The queue of prefixes of `crawl_index`, which the clients feed with the
+/// autocomplete answer to each prefix it hands out.
+struct Crawler<'a, P> {
+    options: &'a CrawlOptions,
+    progress: P,
+    prefixes: VecDeque<String>,
+    /// Failed attempts at the prefix in front of the queue.
+    failures: usize,
+    requests: usize,
+    cities: Vec<City>,
+    failed: Vec<(String, anyhow::Error)>,
+    truncated: Vec<String>,
+}
+
+impl<'a, P: FnMut(&str, usize)> Crawler<'a, P> {
+    fn new(options: &'a CrawlOptions, progress: P) -> Self {
+        Self {
+            options,
+            progress,
+            prefixes: options.initial_prefixes().into(),
+            failures: 0,
+            requests: 0,
+            cities: Vec::new(),
+            failed: Vec::new(),
+            truncated: Vec::new(),
+        }
+    }
+
+    /// The prefix to query next, the same one again after a failure until
+    /// it runs out of retries.
+    fn next_prefix(&self) -> Option<String> {
+        self.prefixes.front().cloned()
+    }
+
+    /// How long to wait before the next request, none before the first.
+    fn delay(&self) -> Option<Duration> {
+        Some(self.options.delay).filter(|delay| self.requests > 0 && !delay.is_zero())
+    }
+
+    fn record(&mut self, found: Result<Vec<City>>) {
+        self.requests += 1;
+        if found.is_err() && self.failures < self.options.retries {
+            self.failures += 1;
+            return;
+        }
+
+        self.failures = 0;
+        let prefix = match self.prefixes.pop_front() {
+            Some(prefix) => prefix,
+            None => return,
+        };
+
+        match found {
+            Ok(found) => {
+                (self.progress)(&prefix, found.len());
+
+                if self.options.should_extend(&prefix, found.len()) {
+                    self.prefixes.extend(self.options.extend(&prefix));
+                } else if self.options.is_truncated(found.len()) {
+                    self.truncated.push(prefix);
+                }
+
+                self.cities.extend(found);
+            }
+            Err(error) => self.failed.push((prefix, error)),
+        }
+    }
+
+    fn finish(self) -> Crawl {
+        Crawl {
+            index: CityIndex::new(self.cities),
+            failed: self.failed,
+            truncated: self.truncated,
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+
+use crate::rank::normalize;
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Forecast {
+    pub days: Vec<DailyForecast>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct DailyForecast {
+    /// As shown on the page, e.g. "Seg 18/10".
+    pub date: String,
+    pub condition: String,
+    /// Degrees Celsius.
+    pub min_temperature: Option<i32>,
+    pub max_temperature: Option<i32>,
+    /// Percentages.
+    pub rain_probability: Option<u8>,
+    pub min_humidity: Option<u8>,
+    pub max_humidity: Option<u8>,
+    pub wind: Option<String>,
+    pub uv_index: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForecastChange {
+    /// The rain probability of a day rose above the threshold.
+    RainAbove {
+        date: String,
+        previous: Option<u8>,
+        current: u8,
+    },
+    /// The condition of a day is not the one previously forecast.
+    Condition {
+        date: String,
+        previous: String,
+        current: String,
+    },
+}
+
+impl Forecast {
+    pub fn day(&self, date: &str) -> Option<&DailyForecast> {
+        self.days.iter().find(|day| day.date == date)
+    }
+
+    /// What changed since a previous forecast, day by day. Days missing from
+    /// the previous forecast only count for rain, as if they were dry.
+    pub fn changes_since(
+        &self,
+        previous: &Forecast,
+        rain_above: Option<u8>,
+    ) -> Vec<ForecastChange> {
+        let mut changes = Vec::new();
+
+        for day in &self.days {
+            let before = previous.day(&day.date);
+
+            let previous_rain = before.and_then(|before| before.rain_probability);
+            if let (Some(threshold), Some(current)) = (rain_above, day.rain_probability) {
+                if current > threshold && previous_rain.is_none_or(|rain| rain <= threshold) {
+                    changes.push(ForecastChange::RainAbove {
+                        date: day.date.clone(),
+                        previous: previous_rain,
+                        current,
+                    });
+                }
+            }
+
+            if let Some(before) = before.filter(|before| !before.condition.is_empty()) {
+                if !day.condition.is_empty()
+                    && normalize(&before.condition) != normalize(&day.condition)
+                {
+                    changes.push(ForecastChange::Condition {
+                        date: day.date.clone(),
+                        previous: before.condition.clone(),
+                        current: day.condition.clone(),
+                    });
+                }
+            }
+        }
+
+        changes
+    }
+}
@@ -0,0 +1,186 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use crate::{rank::normalize, City, CityIndex};
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// IBGE state codes, which are also the first two digits of municipality codes.
+const UF_CODES: [(u32, &str); 27] = [
+    (11, "RO"),
+    (12, "AC"),
+    (13, "AM"),
+    (14, "RR"),
+    (15, "PA"),
+    (16, "AP"),
+    (17, "TO"),
+    (21, "MA"),
+    (22, "PI"),
+    (23, "CE"),
+    (24, "RN"),
+    (25, "PB"),
+    (26, "PE"),
+    (27, "AL"),
+    (28, "SE"),
+    (29, "BA"),
+    (31, "MG"),
+    (32, "ES"),
+    (33, "RJ"),
+    (35, "SP"),
+    (41, "PR"),
+    (42, "SC"),
+    (43, "RS"),
+    (50, "MS"),
+    (51, "MT"),
+    (52, "GO"),
+    (53, "DF"),
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Place {
+    /// IBGE municipality code, e.g. 4205407 for Florianópolis.
+    pub ibge: u32,
+    pub name: String,
+    pub uf: String,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NearbyCity {
+    pub city: City,
+    pub place: Place,
+    pub distance_km: f64,
+}
+
+/// Coordinates of Brazilian municipalities, matched to CPTEC cities by name
+/// and state, since CPTEC ids are not IBGE codes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Gazetteer {
+    places: Vec<Place>,
+}
+
+/// A row of a municipality CSV such as the one from
+/// github.com/kelvins/Municipios-Brasileiros. Other columns are ignored.
+#[derive(Deserialize)]
+struct CsvRecord {
+    codigo_ibge: u32,
+    nome: String,
+    latitude: f64,
+    longitude: f64,
+    #[serde(default)]
+    uf: Option<String>,
+}
+
+fn uf_from_ibge(code: u32) -> Option<&'static str> {
+    let state = code / 100_000;
+    UF_CODES
+        .iter()
+        .find(|(uf_code, _)| *uf_code == state)
+        .map(|(_, uf)| *uf)
+}
+
+/// Great-circle distance between two points, in kilometres.
+pub fn distance_km(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat1, lon1) = (from.0.to_radians(), from.1.to_radians());
+    let (lat2, lon2) = (to.0.to_radians(), to.1.to_radians());
+
+    let a = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+impl Gazetteer {
+    pub fn new(places: Vec<Place>) -> Self {
+        Self { places }
+    }
+
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("meteo").join("gazetteer.json"))
+    }
+
+    /// Reads a CSV with `codigo_ibge`, `nome`, `latitude` and `longitude`
+    /// columns, and optionally `uf`. Without it, the state comes from the code.
+    pub fn from_csv(reader: impl Read) -> Result<Self> {
+        let mut places = Vec::new();
+
+        for record in csv::Reader::from_reader(reader).deserialize() {
+            let record: CsvRecord = record.context("Invalid gazetteer CSV")?;
+
+            let uf = match record.uf {
+                Some(uf) => uf.to_uppercase(),
+                None => uf_from_ibge(record.codigo_ibge)
+                    .with_context(|| format!("Unknown IBGE code {}", record.codigo_ibge))?
+                    .to_owned(),
+            };
+
+            places.push(Place {
+                ibge: record.codigo_ibge,
+                name: record.nome,
+                uf,
+                latitude: record.latitude,
+                longitude: record.longitude,
+            });
+        }
+
+        Ok(Self { places })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = fs::read(path)
+            .with_context(|| format!("Could not read gazetteer {}", path.display()))?;
+
+        serde_json::from_slice(&json)
+            .with_context(|| format!("Invalid gazetteer {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(path, serde_json::to_vec(self)?)
+            .with_context(|| format!("Could not write gazetteer {}", path.display()))
+    }
+
+    pub fn places(&self) -> &[Place] {
+        &self.places
+    }
+
+    /// Every indexed city with known coordinates, nearest first.
+    pub fn nearest(&self, index: &CityIndex, latitude: f64, longitude: f64) -> Vec<NearbyCity> {
+        let by_name: HashMap<(String, &str), &Place> = self
+            .places
+            .iter()
+            .map(|place| ((normalize(&place.name), place.uf.as_str()), place))
+            .collect();
+
+        let mut nearby: Vec<NearbyCity> = index
+            .cities()
+            .iter()
+            .filter_map(|city| {
+                let place = by_name.get(&(normalize(&city.name), city.uf.as_str()))?;
+
+                Some(NearbyCity {
+                    city: city.clone(),
+                    place: (*place).clone(),
+                    distance_km: distance_km(
+                        (latitude, longitude),
+                        (place.latitude, place.longitude),
+                    ),
+                })
+            })
+            .collect();
+
+        nearby.sort_by(|a, b| a.distance_km.total_cmp(&b.distance_km));
+        nearby
+    }
+}
@@ -0,0 +1,184 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::BTreeMap,
+    convert::TryFrom,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    city::RawCity,
+    rank::{city_score, normalize, rank_cities},
+    City,
+};
+
+/// Minimum score for a city to be considered a match by `CityIndex::search`,
+/// which lets through substrings, subsequences and small typos.
+const MIN_SCORE: u32 = 70;
+
+/// Every city known to the CPTEC autocomplete, for searching without network.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "Vec<RawCity>", try_from = "Vec<RawCity>")]
+pub struct CityIndex {
+    cities: Vec<City>,
+}
+
+impl From<CityIndex> for Vec<RawCity> {
+    fn from(index: CityIndex) -> Self {
+        index.cities.iter().map(RawCity::from).collect()
+    }
+}
+
+impl TryFrom<Vec<RawCity>> for CityIndex {
+    type Error = crate::CityError;
+
+    fn try_from(raw: Vec<RawCity>) -> Result<Self, Self::Error> {
+        let cities = raw
+            .into_iter()
+            .map(City::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self::new(cities))
+    }
+}
+
+impl CityIndex {
+    /// Deduplicates the cities by id and sorts them by name.
+    pub fn new(cities: Vec<City>) -> Self {
+        let by_id: BTreeMap<u32, City> = cities.into_iter().map(|city| (city.id, city)).collect();
+
+        let mut cities: Vec<City> = by_id.into_values().collect();
+        cities.sort_by_cached_key(|city| (normalize(&city.name), city.uf.clone()));
+
+        Self { cities }
+    }
+
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("meteo").join("cities.json"))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = fs::read(path)
+            .with_context(|| format!("Could not read city index {}", path.display()))?;
+
+        serde_json::from_slice(&json)
+            .with_context(|| format!("Invalid city index {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(path, serde_json::to_vec(self)?)
+            .with_context(|| format!("Could not write city index {}", path.display()))
+    }
+
+    pub fn cities(&self) -> &[City] {
+        &self.cities
+    }
+
+    pub fn len(&self) -> usize {
+        self.cities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cities.is_empty()
+    }
+
+    /// Finds the cities matching the query, best match first.
+    pub fn search(&self, query: &str) -> Vec<City> {
+        let normalized = normalize(query);
+
+        let mut matches: Vec<City> = self
+            .cities
+            .iter()
+            .filter(|city| city_score(&normalized, city) >= MIN_SCORE)
+            .cloned()
+            .collect();
+
+        rank_cities(query, &mut matches);
+        matches
+    }
+}
+
+/// How `CptecClient::crawl_index` walks the autocomplete endpoint.
+#[derive(Debug, Clone)]
+pub struct CrawlOptions {
+    /// Characters prefixes are made of. Separators (space, hyphen and
+    /// apostrophe) never start a prefix nor follow one another, so names
+    /// like "São José" or "Santa Bárbara d'Oeste" can be told apart past
+    /// their first word.
+    pub alphabet: Vec<char>,
+    /// Length of the first prefixes queried.
+    pub prefix_len: usize,
+    /// A response with at least this many cities is assumed to be cut off by
+    /// the server, so its prefix is extended by one more character.
+    pub truncated_at: usize,
+    pub max_prefix_len: usize,
+    /// How many more times a prefix is queried after a failure before it is
+    /// given up on.
+    pub retries: usize,
+    /// Pause between two requests, to go easy on CPTEC.
+    pub delay: Duration,
+}
+
+/// The outcome of `CptecClient::crawl_index`: the cities found, and the
+/// prefixes the index may be missing cities of, either because they kept
+/// failing or because they were still cut off at `max_prefix_len`.
+#[derive(Debug)]
+pub struct Crawl {
+    pub index: CityIndex,
+    pub failed: Vec<(String, anyhow::Error)>,
+    pub truncated: Vec<String>,
+}
+
+const SEPARATORS: &[char] = &[' ', '-', '\''];
+
+impl Default for CrawlOptions {
+    fn default() -> Self {
+        Self {
+            alphabet: "abcdefghijklmnopqrstuvwxyzáâãçéêíóôõú -'".chars().collect(),
+            prefix_len: 2,
+            truncated_at: 10,
+            max_prefix_len: 4,
+            retries: 2,
+            delay: Duration::from_millis(200),
+        }
+    }
+}
+
+impl CrawlOptions {
+    pub(crate) fn initial_prefixes(&self) -> Vec<String> {
+        (0..self.prefix_len).fold(vec![String::new()], |prefixes, _| {
+            prefixes
+                .iter()
+                .flat_map(|prefix| self.extend(prefix))
+                .collect()
+        })
+    }
+
+    pub(crate) fn extend(&self, prefix: &str) -> Vec<String> {
+        let after_separator = prefix
+            .chars()
+            .last()
+            .is_none_or(|last| SEPARATORS.contains(&last));
+
+        self.alphabet
+            .iter()
+            .filter(|c| !(after_separator && SEPARATORS.contains(c)))
+            .map(|c| format!("{}{}", prefix, c))
+            .collect()
+    }
+
+    pub(crate) fn is_truncated(&self, results: usize) -> bool {
+        results >= self.truncated_at
+    }
+
+    pub(crate) fn should_extend(&self, prefix: &str, results: usize) -> bool {
+        self.is_truncated(results) && prefix.chars().count() < self.max_prefix_len
+    }
+}
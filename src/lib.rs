@@ -0,0 +1,21 @@
+mod archive;
+mod cache;
+mod city;
+mod client;
+mod forecast;
+mod gazetteer;
+mod index;
+mod rank;
+mod scrape;
+
+pub use archive::{Archive, ArchiveEntry};
+pub use cache::{Cache, CacheMode, Resource};
+pub use city::{City, CityError, RawCity};
+#[cfg(feature = "blocking")]
+pub use client::CptecClient;
+pub use client::{AsyncCptecClient, DEFAULT_BASE_URL};
+pub use forecast::{DailyForecast, Forecast, ForecastChange};
+pub use gazetteer::{distance_km, Gazetteer, NearbyCity, Place};
+pub use index::{CityIndex, Crawl, CrawlOptions};
+pub use rank::{normalize, rank_cities};
+pub use scrape::{scrape_forecast, scrape_meteogram_url, ScrapeError};
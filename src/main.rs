@@ -1,132 +1,857 @@
-use anyhow::{Context, Result};
+mod cli;
+
+use anyhow::{bail, Context, Result};
 use clap::Parser;
-use percent_encoding::percent_decode_str;
-use reqwest::blocking::Client;
-use select::{
-    document::Document,
-    predicate::{Attr, Name, Predicate},
+use cli::{
+    alert, batch,
+    config::{self, Config},
+    history::fetch_meteogram,
+    metrics::Metrics,
+    mqtt::{self, Publisher, Topics},
+    output::{print_cities, print_forecast, print_history, Format},
+    save::{self, output_path, save_meteogram, show_meteogram, Overwrite},
+    select::{select_city, Selection, UfFilter},
+    serve::MeteoServer,
+    template,
+    terminal::{self, Protocol},
+    watch::{self, Display},
+    Exit,
+};
+use meteo::{
+    Archive, Cache, CacheMode, City, CityIndex, CptecClient, CrawlOptions, Gazetteer, Resource,
+    DEFAULT_BASE_URL,
 };
-use serde::Deserialize;
+
+use chrono::Local;
 
 use std::{
-    env::temp_dir,
-    fmt::Display,
     fs::File,
-    io::{stdin, Write},
+    io::{stderr, stdout, IsTerminal},
     path::{Path, PathBuf},
+    process::ExitCode,
+    time::Duration,
 };
 
-#[allow(dead_code)]
-#[derive(Debug, Deserialize)]
-struct City {
-    id: String,
-    label: String,
-    value: String,
-    custom: String,
+#[derive(clap::Parser)]
+struct Args {
+    #[clap(flatten)]
+    client: ClientArgs,
+
+    /// Config file with the favourite cities
+    #[clap(long, global = true, env = "METEO_CONFIG")]
+    config: Option<PathBuf>,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    #[clap(flatten)]
+    meteogram: MeteogramArgs,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// List the cities matching a query
+    Search(SearchArgs),
+    /// Fetch the meteogram of a city (the default)
+    Meteogram(MeteogramArgs),
+    /// Print the daily forecast of a city
+    Forecast(ForecastArgs),
+    /// Open the CPTEC page of a city in the browser
+    Open(CityArgs),
+    /// Fetch the meteograms of many cities at once
+    Batch(BatchArgs),
+    /// List the archived meteograms of a city
+    History(HistoryArgs),
+    /// Fetch the meteogram periodically and show it whenever it changes
+    Watch(WatchArgs),
+    /// Warn when the forecast changed since the last time it was checked
+    Alert(AlertArgs),
+    /// Serve cities, meteograms, forecasts and metrics over HTTP
+    Serve(ServeArgs),
+    /// Print forecast metrics in the Prometheus text format
+    ExportMetrics(ExportMetricsArgs),
+    /// Publish forecasts to MQTT, with Home Assistant discovery
+    Mqtt(MqttArgs),
+    /// Manage favourite cities
+    #[clap(subcommand)]
+    Fav(FavCommand),
+    /// Manage the local city index
+    #[clap(subcommand)]
+    Index(IndexCommand),
+    /// Show the effective configuration
+    Config,
+}
+
+#[derive(clap::Subcommand)]
+enum IndexCommand {
+    /// Crawl the CPTEC autocomplete and store every city it knows
+    Update,
+    /// Import municipality coordinates from an IBGE CSV, for --lat/--lon
+    Gazetteer { csv: PathBuf },
+}
+
+#[derive(clap::Subcommand)]
+enum FavCommand {
+    /// Save a city under an alias
+    Add(FavAddArgs),
+    /// Forget a favourite
+    Remove { alias: String },
+    /// List the favourites
+    List,
+}
+
+#[derive(clap::Args)]
+struct ClientArgs {
+    #[clap(long, global = true, env = "METEO_BASE_URL", default_value = DEFAULT_BASE_URL)]
+    base_url: String,
+
+    /// Neither read from nor write to the HTTP cache
+    #[clap(long, global = true)]
+    no_cache: bool,
+
+    /// Ignore cached responses, but store the new ones
+    #[clap(long, global = true, conflicts_with = "no-cache")]
+    refresh: bool,
+
+    /// Search cities only in the local index, see `meteo index update`
+    #[clap(long, global = true)]
+    offline: bool,
+
+    /// City index file
+    #[clap(long, global = true, env = "METEO_CITY_INDEX")]
+    city_index: Option<PathBuf>,
+
+    /// Directory where every fetched meteogram is archived
+    #[clap(long, global = true, env = "METEO_ARCHIVE")]
+    archive: Option<PathBuf>,
+
+    /// Municipality coordinates file, imported by `meteo index gazetteer`
+    #[clap(long, global = true, env = "METEO_GAZETTEER")]
+    gazetteer: Option<PathBuf>,
+
+    /// Directory where `meteo alert` keeps the forecasts it has seen
+    #[clap(long, global = true, env = "METEO_ALERT_STATE")]
+    alert_state: Option<PathBuf>,
+
+    /// Do not archive fetched meteograms
+    #[clap(long, global = true)]
+    no_archive: bool,
 }
 
-impl Display for City {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let name = percent_decode_str(&self.label)
-            .decode_utf8_lossy()
-            .replace('+', " ");
+impl ClientArgs {
+    fn cache(&self) -> Option<PathBuf> {
+        Cache::default_dir().filter(|_| !self.no_cache)
+    }
+
+    fn open_cache(&self) -> Result<Option<Cache>> {
+        let dir = match self.cache() {
+            Some(dir) => dir,
+            None => return Ok(None),
+        };
+
+        let mode = if self.refresh {
+            CacheMode::Refresh
+        } else {
+            CacheMode::Use
+        };
+
+        Ok(Some(Cache::open(dir, mode)?))
+    }
+
+    fn client(&self, cache: Option<Cache>) -> Result<CptecClient> {
+        let client = CptecClient::with_base_url(&self.base_url)?;
+
+        Ok(match cache {
+            Some(cache) => client.with_cache(cache),
+            None => client,
+        })
+    }
+
+    fn index_path(&self) -> Result<PathBuf> {
+        match &self.city_index {
+            Some(path) => Ok(path.clone()),
+            None => CityIndex::default_path().context("Could not find a data directory"),
+        }
+    }
+
+    fn archive_dir(&self) -> Result<PathBuf> {
+        match &self.archive {
+            Some(dir) => Ok(dir.clone()),
+            None => Archive::default_dir().context("Could not find a data directory"),
+        }
+    }
+
+    /// The archive fetched meteograms are recorded in, unless disabled.
+    fn archive(&self) -> Result<Option<Archive>> {
+        if self.no_archive {
+            return Ok(None);
+        }
+
+        Ok(Some(Archive::open(self.archive_dir()?)?))
+    }
+
+    fn gazetteer_path(&self) -> Result<PathBuf> {
+        match &self.gazetteer {
+            Some(path) => Ok(path.clone()),
+            None => Gazetteer::default_path().context("Could not find a data directory"),
+        }
+    }
+
+    /// Where `meteo alert` keeps the forecasts it has seen.
+    fn forecasts_dir(&self) -> Result<PathBuf> {
+        match &self.alert_state {
+            Some(dir) => Ok(dir.clone()),
+            None => alert::default_state_dir().context("Could not find a data directory"),
+        }
+    }
+
+    fn nearest_city(&self, latitude: f64, longitude: f64) -> Result<City> {
+        let index = CityIndex::load(&self.index_path()?)
+            .context("Looking up coordinates needs `meteo index update` first")?;
+        let gazetteer = Gazetteer::load(&self.gazetteer_path()?)
+            .context("Looking up coordinates needs `meteo index gazetteer <csv>` first")?;
 
-        write!(f, "{}", name)
+        let nearest = gazetteer
+            .nearest(&index, latitude, longitude)
+            .into_iter()
+            .next()
+            .context("No indexed city has known coordinates")?;
+
+        eprintln!("{} ({:.1} km)", nearest.city, nearest.distance_km);
+        Ok(nearest.city)
+    }
+
+    /// Makes searches use the city index when there is one.
+    fn with_index(&self, client: CptecClient) -> Result<CptecClient> {
+        let path = self.index_path()?;
+
+        if path.exists() {
+            Ok(client.with_index(CityIndex::load(&path)?))
+        } else if self.offline {
+            bail!(
+                "No city index at {}, run `meteo index update` first",
+                path.display()
+            )
+        } else {
+            Ok(client)
+        }
     }
 }
 
-fn search_cities(query: &str) -> Result<Vec<City>> {
-    let url = "https://tempo.cptec.inpe.br/autocomplete";
-    let params = [("term", query)];
+fn search_and_select(client: &CptecClient, query: &str, selection: &Selection) -> Result<City> {
+    let cities = selection.uf.apply(client.search(query)?);
+    select_city(&cities, query, selection).cloned()
+}
+
+#[derive(clap::Args)]
+struct CityArgs {
+    /// City name or favourite alias; the default favourite if omitted
+    query: Option<String>,
 
-    let client = Client::new();
-    let response = client.get(url).query(&params).send()?;
+    /// Pick the city nearest to this latitude instead of searching
+    #[clap(
+        long,
+        allow_hyphen_values = true,
+        requires = "lon",
+        conflicts_with = "query"
+    )]
+    lat: Option<f64>,
 
-    let json = response.text()?;
-    let cities: Vec<City> = serde_json::from_str(&json)?;
+    /// Pick the city nearest to this longitude instead of searching
+    #[clap(long, allow_hyphen_values = true, requires = "lat")]
+    lon: Option<f64>,
 
-    Ok(cities)
+    #[clap(flatten)]
+    selection: Selection,
 }
 
-fn select_city_prompt(cities: &[City]) -> Result<&City> {
-    for (i, city) in cities.iter().enumerate() {
-        println!("[{:2}] {}", i, city);
+impl CityArgs {
+    fn resolve(&self, client: &CptecClient, config: &Config, args: &ClientArgs) -> Result<City> {
+        if let (Some(latitude), Some(longitude)) = (self.lat, self.lon) {
+            return args.nearest_city(latitude, longitude);
+        }
+
+        let query = match self.query.as_deref() {
+            Some(query) => query,
+            None => return config.default_favourite(),
+        };
+
+        match config.favourite(query)? {
+            Some(city) => Ok(city),
+            None => search_and_select(client, query, &self.selection),
+        }
     }
+}
+
+#[derive(clap::Args)]
+struct FavAddArgs {
+    alias: String,
+
+    query: String,
+
+    #[clap(flatten)]
+    selection: Selection,
 
-    println!("\nDigite o número da cidade desejada: ");
+    /// Use this favourite when no query is given
+    #[clap(long)]
+    default: bool,
+}
 
-    let mut input_buffer = String::new();
-    stdin().read_line(&mut input_buffer)?;
+#[derive(clap::Args)]
+struct SearchArgs {
+    query: String,
 
-    let index = input_buffer.trim().parse::<usize>()?;
+    #[clap(flatten)]
+    uf: UfFilter,
 
-    Ok(&cities[index])
+    #[clap(long, arg_enum, default_value = "table")]
+    format: Format,
 }
 
-fn forecast_url(city: &City) -> String {
-    format!("https://tempo.cptec.inpe.br/{}", city.custom)
+#[derive(clap::Args)]
+struct MeteogramArgs {
+    #[clap(flatten)]
+    city: CityArgs,
+
+    #[clap(flatten)]
+    output: OutputArgs,
 }
 
-fn fetch_forecast_page(city: &City) -> Result<String> {
-    let url = forecast_url(city);
+#[derive(clap::Args)]
+struct OutputArgs {
+    /// Output file or directory, or - for stdout; may contain {city}, {uf},
+    /// {id}, {date}, {time} and {ext}
+    #[clap(short)]
+    output: Option<String>,
 
-    let client = Client::new();
-    let response = client.get(url).send()?;
+    #[clap(flatten)]
+    overwrite: Overwrite,
 
-    Ok(response.text()?)
+    /// Render the meteogram in the terminal instead of opening a viewer
+    #[clap(long, conflicts_with = "output")]
+    terminal: bool,
+
+    /// Graphics protocol used by --terminal
+    #[clap(long, arg_enum, default_value = "auto")]
+    protocol: Protocol,
 }
 
-fn scrape_meteogram_url(page_contents: &str) -> Option<String> {
-    let doc = Document::from(page_contents);
+#[derive(clap::Args)]
+struct BatchArgs {
+    /// File with favourites, aliases or queries, one per line, or a TOML
+    /// file with a `cities` list
+    file: PathBuf,
+
+    #[clap(long, default_value = ".")]
+    out_dir: PathBuf,
 
-    let selector = Name("div")
-        .and(Attr("id", "meteograma"))
-        .descendant(Name("img"));
+    /// File name template, see `meteo meteogram -o`
+    #[clap(long, default_value = template::DEFAULT_TEMPLATE)]
+    name: String,
 
-    let img = doc.find(selector).next();
+    /// How many cities to fetch at the same time
+    #[clap(long, short, default_value = "4")]
+    jobs: usize,
 
-    img.and_then(|node| node.attr("src"))
-        .map(|url| url.to_owned())
+    #[clap(flatten)]
+    overwrite: Overwrite,
 }
 
-fn fetch_meteogram(city: &City) -> Result<Vec<u8>> {
-    let page_contents = fetch_forecast_page(city)?;
-    let url = scrape_meteogram_url(&page_contents).context("Could not find meteogram URL")?;
+#[derive(clap::Args)]
+struct HistoryArgs {
+    #[clap(subcommand)]
+    command: Option<HistoryCommand>,
+
+    #[clap(flatten)]
+    city: CityArgs,
 
-    let response = Client::new().get(url).send()?;
-    Ok(response.bytes()?.to_vec())
+    #[clap(long, arg_enum, default_value = "table")]
+    format: Format,
 }
 
-fn save_meteogram(bytes: &[u8], path: &Path) -> Result<()> {
-    let mut file = File::create(path)?;
-    file.write_all(bytes)?;
-    Ok(())
+#[derive(clap::Subcommand)]
+enum HistoryCommand {
+    /// Display an archived meteogram
+    Show(HistoryShowArgs),
 }
 
-fn show_meteogram(bytes: &[u8]) -> Result<()> {
-    let temp_path = temp_dir().join("meteo.png");
-    save_meteogram(bytes, &temp_path)?;
-    open::that(&temp_path)?;
-    Ok(())
+#[derive(clap::Args)]
+struct HistoryShowArgs {
+    /// Entry id, as listed by `meteo history`
+    id: usize,
+
+    #[clap(flatten)]
+    output: OutputArgs,
 }
 
-#[derive(clap::Parser)]
-struct Args {
-    query: String,
+#[derive(clap::Args)]
+struct WatchArgs {
+    #[clap(flatten)]
+    city: CityArgs,
+
+    /// Time between fetches, like 90s, 30m or 1h, at least 1m
+    #[clap(long, default_value = "30m", parse(try_from_str = watch::parse_poll_interval))]
+    every: Duration,
+
+    /// Stop after this many fetches
+    #[clap(long)]
+    count: Option<usize>,
 
+    /// Keep this file up to date instead of opening a viewer
     #[clap(short)]
     output: Option<PathBuf>,
+
+    /// Render the meteogram in the terminal instead of opening a viewer
+    #[clap(long, conflicts_with = "output")]
+    terminal: bool,
+
+    /// Graphics protocol used by --terminal
+    #[clap(long, arg_enum, default_value = "auto")]
+    protocol: Protocol,
+}
+
+#[derive(clap::Args)]
+struct AlertArgs {
+    #[clap(flatten)]
+    city: CityArgs,
+
+    /// Alert when the chance of rain of a day rises above this, like 60%
+    #[clap(long, parse(try_from_str = alert::parse_percentage))]
+    rain_above: Option<u8>,
+}
+
+#[derive(clap::Args)]
+struct ExportMetricsArgs {
+    /// Favourites, aliases or queries; every favourite if omitted
+    cities: Vec<String>,
+}
+
+#[derive(clap::Args)]
+struct MqttArgs {
+    /// Favourites, aliases or queries; every favourite if omitted
+    cities: Vec<String>,
+
+    /// Broker address
+    #[clap(long, default_value = "localhost:1883")]
+    broker: String,
+
+    #[clap(long, env = "METEO_MQTT_USERNAME")]
+    username: Option<String>,
+
+    #[clap(long, env = "METEO_MQTT_PASSWORD", requires = "username")]
+    password: Option<String>,
+
+    /// Prefix of the state topics
+    #[clap(long, default_value = "meteo")]
+    topic_prefix: String,
+
+    /// Prefix Home Assistant listens to for discovery
+    #[clap(long, default_value = "homeassistant")]
+    discovery_prefix: String,
+
+    /// Time between publications, like 90s, 30m or 1h, at least 1m
+    #[clap(long, default_value = "30m", parse(try_from_str = watch::parse_poll_interval))]
+    every: Duration,
+
+    /// Stop after this many publications
+    #[clap(long)]
+    count: Option<usize>,
+
+    /// Print the messages instead of publishing them
+    #[clap(long)]
+    dry_run: bool,
+}
+
+#[derive(clap::Args)]
+struct ServeArgs {
+    /// Address to listen on
+    #[clap(long, default_value = "127.0.0.1:8080")]
+    bind: String,
+}
+
+#[derive(clap::Args)]
+struct ForecastArgs {
+    #[clap(flatten)]
+    city: CityArgs,
+
+    #[clap(long, arg_enum, default_value = "table")]
+    format: Format,
+}
+
+fn search(client: &CptecClient, args: SearchArgs) -> Result<()> {
+    let cities = args.uf.apply(client.search(&args.query)?);
+    print_cities(&cities, args.format, &mut stdout().lock())
+}
+
+fn meteogram(
+    client: &CptecClient,
+    config: &Config,
+    client_args: &ClientArgs,
+    args: MeteogramArgs,
+) -> Result<()> {
+    let city = args.city.resolve(client, config, client_args)?;
+    let archive = client_args.archive()?;
+    let meteogram = fetch_meteogram(client, archive.as_ref(), &city)?;
+
+    output_meteogram(&meteogram, &city, args.output)
+}
+
+fn output_meteogram(meteogram: &[u8], city: &City, args: OutputArgs) -> Result<()> {
+    let target = match args.output {
+        Some(target) if target == save::STDOUT => return save::write_stdout(meteogram),
+        Some(target) => target,
+        None if args.terminal => return terminal::show_meteogram(meteogram, args.protocol),
+        None => return show_meteogram(meteogram),
+    };
+
+    let path = output_path(&target, city, &Local::now())?;
+    if !save_meteogram(meteogram, &path, &args.overwrite)? {
+        eprintln!("{} já existe, mantido", path.display());
+    }
+
+    Ok(())
+}
+
+fn watch(
+    client: &CptecClient,
+    config: &Config,
+    client_args: &ClientArgs,
+    args: WatchArgs,
+) -> Result<()> {
+    let city = args.city.resolve(client, config, client_args)?;
+    let archive = client_args.archive()?;
+
+    let display = match args.output {
+        Some(path) => Display::File(path),
+        None if args.terminal => Display::Terminal(args.protocol),
+        None => Display::Viewer,
+    };
+
+    watch::run(
+        client,
+        archive.as_ref(),
+        &city,
+        args.every,
+        args.count,
+        &display,
+    )
+}
+
+/// The cities named on the command line, or else every favourite. Names
+/// that cannot be resolved are reported and skipped.
+fn metrics_cities(client: &CptecClient, config: &Config, names: &[String]) -> Result<Vec<City>> {
+    if names.is_empty() {
+        return config
+            .favourites
+            .values()
+            .map(|favourite| favourite.city())
+            .collect();
+    }
+
+    Ok(names
+        .iter()
+        .filter_map(|name| match batch::resolve(client, config, name) {
+            Ok(city) => Some(city),
+            Err(error) => {
+                eprintln!("{}: {:#}", name, error);
+                None
+            }
+        })
+        .collect())
+}
+
+fn export_metrics(client: &CptecClient, config: &Config, args: ExportMetricsArgs) -> Result<()> {
+    let metrics = Metrics::default();
+    for city in metrics_cities(client, config, &args.cities)? {
+        metrics.collect(client, &city);
+    }
+
+    metrics.render(&mut stdout().lock())?;
+    Ok(())
+}
+
+fn publish_mqtt(client: &CptecClient, config: &Config, args: MqttArgs) -> Result<()> {
+    let cities = metrics_cities(client, config, &args.cities)?;
+    if cities.is_empty() {
+        bail!("No cities to publish, name some or add favourites");
+    }
+
+    let publisher = if args.dry_run {
+        Publisher::DryRun
+    } else {
+        let credentials = args
+            .username
+            .as_deref()
+            .map(|username| (username, args.password.as_deref().unwrap_or_default()));
+        Publisher::connect(&args.broker, credentials)?
+    };
+
+    let topics = Topics {
+        prefix: args.topic_prefix,
+        discovery_prefix: args.discovery_prefix,
+    };
+
+    mqtt::run(client, &cities, &topics, publisher, args.every, args.count)
+}
+
+fn serve(
+    client: &CptecClient,
+    config: &Config,
+    client_args: &ClientArgs,
+    args: ServeArgs,
+) -> Result<()> {
+    let path = client_args.index_path()?;
+    let index = if path.exists() {
+        CityIndex::load(&path)?
+    } else {
+        CityIndex::default()
+    };
+
+    let archive = client_args.archive()?;
+    MeteoServer::new(client, archive.as_ref(), index.cities())
+        .with_watched(metrics_cities(client, config, &[])?)
+        .run(&args.bind)
+}
+
+fn history(
+    client: &CptecClient,
+    config: &Config,
+    client_args: &ClientArgs,
+    args: HistoryArgs,
+) -> Result<()> {
+    let archive = Archive::open(client_args.archive_dir()?)?;
+
+    if let Some(HistoryCommand::Show(args)) = args.command {
+        let entry = archive
+            .entry(args.id)?
+            .with_context(|| format!("No archived meteogram with id {}", args.id))?;
+
+        eprintln!(
+            "{} {} {}",
+            entry.city,
+            entry
+                .fetched_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M"),
+            entry.url
+        );
+        return output_meteogram(&archive.image(&entry)?, &entry.city, args.output);
+    }
+
+    let city = args.city.resolve(client, config, client_args)?;
+    let entries = archive.history(&city)?;
+    print_history(&entries, args.format, &mut stdout().lock())
+}
+
+fn forecast(
+    client: &CptecClient,
+    config: &Config,
+    client_args: &ClientArgs,
+    args: ForecastArgs,
+) -> Result<()> {
+    let city = args.city.resolve(client, config, client_args)?;
+    let forecast = client.forecast(&city)?;
+    print_forecast(&forecast, args.format, &mut stdout().lock())
+}
+
+fn open_page(
+    client: &CptecClient,
+    config: &Config,
+    client_args: &ClientArgs,
+    args: CityArgs,
+) -> Result<()> {
+    let city = args.resolve(client, config, client_args)?;
+    open::that(client.forecast_url(&city)?.as_str())?;
+    Ok(())
+}
+
+fn fav(client: &CptecClient, config_path: &Path, command: FavCommand) -> Result<()> {
+    let mut config = Config::load(config_path)?;
+
+    match command {
+        FavCommand::Add(args) => {
+            let city = search_and_select(client, &args.query, &args.selection)?;
+            config.add_favourite(&args.alias, &city, args.default);
+            println!("{} -> {}", args.alias, city);
+        }
+        FavCommand::Remove { alias } => config.remove_favourite(&alias)?,
+        FavCommand::List => {
+            for (alias, favourite) in &config.favourites {
+                let marker = if config.default.as_ref() == Some(alias) {
+                    " (padrão)"
+                } else {
+                    ""
+                };
+
+                println!("{} -> {}{}", alias, favourite.city()?, marker);
+            }
+
+            return Ok(());
+        }
+    }
+
+    config.save(config_path)
+}
+
+fn index(client: &CptecClient, args: &ClientArgs, command: IndexCommand) -> Result<()> {
+    match command {
+        IndexCommand::Update => {
+            let path = args.index_path()?;
+            let interactive = stderr().is_terminal();
+
+            let crawl = client.crawl_index(&CrawlOptions::default(), |prefix, found| {
+                if interactive {
+                    eprint!("\r{:<6} {:>3} cidades", prefix, found);
+                }
+            });
+
+            if interactive {
+                eprintln!();
+            }
+
+            for (prefix, error) in &crawl.failed {
+                eprintln!("Prefixo {:?} ignorado: {:#}", prefix, error);
+            }
+            for prefix in &crawl.truncated {
+                eprintln!(
+                    "Prefixo {:?} ainda truncado, algumas cidades podem faltar",
+                    prefix
+                );
+            }
+
+            let index = crawl.index;
+            if index.is_empty() {
+                if let Some((_, error)) = crawl.failed.into_iter().next() {
+                    return Err(error.context("Could not crawl the city index"));
+                }
+            }
+
+            index.save(&path)?;
+            println!("{} cidades em {}", index.len(), path.display());
+        }
+        IndexCommand::Gazetteer { csv } => {
+            let path = args.gazetteer_path()?;
+            let file =
+                File::open(&csv).with_context(|| format!("Could not open {}", csv.display()))?;
+
+            let gazetteer = Gazetteer::from_csv(file)?;
+            gazetteer.save(&path)?;
+            println!(
+                "{} municípios em {}",
+                gazetteer.places().len(),
+                path.display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn show_config(args: &ClientArgs, config_path: &Path) -> Result<()> {
+    let config = Config::load(config_path)?;
+
+    println!("config_file = {}", config_path.display());
+    println!("base_url = {}", args.base_url);
+
+    match args.cache() {
+        Some(dir) => println!("cache_dir = {}", dir.display()),
+        None => println!("cache_dir = (disabled)"),
+    }
+
+    println!("city_index = {}", args.index_path()?.display());
+
+    match config.default {
+        Some(alias) => println!("default = {}", alias),
+        None => println!("default = (none)"),
+    }
+
+    Ok(())
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+fn run(args: Args) -> Result<()> {
+    let config_path = match args.config {
+        Some(path) => path,
+        None => config::default_path().context("Could not find a config directory")?,
+    };
+
+    let command = match args.command {
+        Some(Command::Config) => return show_config(&args.client, &config_path),
+        Some(command) => command,
+        None => Command::Meteogram(args.meteogram),
+    };
+
+    let mut cache = args.client.open_cache()?;
+    if let (Command::Watch(_), Some(cache)) = (&command, &mut cache) {
+        // Every round of a watch asks the server, which answers 304 when
+        // nothing changed.
+        cache.set_ttl(Resource::ForecastPage, Duration::ZERO);
+        cache.set_ttl(Resource::Meteogram, Duration::ZERO);
+    }
+
+    let client = args.client.client(cache)?;
 
-    let cities = search_cities(&args.query)?;
-    let selected_city = select_city_prompt(&cities)?;
-    let meteogram = fetch_meteogram(selected_city)?;
+    if let Command::Index(command) = command {
+        return index(&client, &args.client, command);
+    }
+
+    let client = args.client.with_index(client)?;
+
+    let config = match command {
+        Command::Fav(command) => return fav(&client, &config_path, command),
+        _ => Config::load(&config_path)?,
+    };
+
+    match command {
+        Command::Search(command) => search(&client, command),
+        Command::Meteogram(command) => meteogram(&client, &config, &args.client, command),
+        Command::Forecast(command) => forecast(&client, &config, &args.client, command),
+        Command::Open(command) => open_page(&client, &config, &args.client, command),
+        Command::History(command) => history(&client, &config, &args.client, command),
+        Command::Watch(command) => watch(&client, &config, &args.client, command),
+        Command::Serve(command) => serve(&client, &config, &args.client, command),
+        Command::ExportMetrics(command) => export_metrics(&client, &config, command),
+        Command::Mqtt(command) => publish_mqtt(&client, &config, command),
+        Command::Alert(command) => {
+            let city = command.city.resolve(&client, &config, &args.client)?;
+            alert::run(
+                &client,
+                &city,
+                &args.client.forecasts_dir()?,
+                command.rain_above,
+            )
+        }
+        Command::Batch(command) => {
+            let names = batch::read_cities(&command.file)?;
+            batch::run(
+                &client,
+                &config,
+                args.client.archive()?.as_ref(),
+                &names,
+                &batch::Output {
+                    dir: &command.out_dir,
+                    name_template: &command.name,
+                    overwrite: &command.overwrite,
+                },
+                command.jobs,
+            )
+        }
+        Command::Fav(_) | Command::Index(_) | Command::Config => unreachable!(),
+    }
+}
 
-    match args.output {
-        Some(path) => save_meteogram(&meteogram, &path),
-        None => show_meteogram(&meteogram),
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => match error.downcast_ref::<Exit>() {
+            Some(Exit::Quit) => Exit::Quit.code(),
+            Some(exit) => {
+                eprintln!("{}", exit);
+                exit.code()
+            }
+            None => {
+                eprintln!("Error: {:?}", error);
+                ExitCode::FAILURE
+            }
+        },
     }
 }
@@ -0,0 +1,81 @@
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use crate::City;
+
+/// Strips diacritics, case-folds and collapses whitespace and dashes, so that
+/// "São  José-SC" and "sao jose sc" compare equal.
+pub fn normalize(text: &str) -> String {
+    let folded: String = text
+        .nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .map(|c| if c == '-' { ' ' } else { c })
+        .collect();
+
+    folded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut haystack = haystack.chars();
+    needle.chars().all(|c| haystack.any(|h| h == c))
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            let cost = usize::from(ca != *cb);
+            row[j + 1] = (diagonal + cost).min(row[j] + 1).min(above + 1);
+            diagonal = above;
+        }
+    }
+
+    row[b.len()]
+}
+
+/// How well a city matches the query, from 0 (not at all) to 1000 (same
+/// name). Both arguments must already be normalized.
+fn score(query: &str, name: &str, label: &str) -> u32 {
+    if query.is_empty() {
+        return 0;
+    }
+
+    // Shorter names win among prefix and substring matches.
+    let length_penalty = (name.chars().count() as u32).min(99);
+
+    if name == query {
+        1000
+    } else if label == query {
+        950
+    } else if name.starts_with(query) {
+        800 - length_penalty
+    } else if name.split(' ').any(|word| word.starts_with(query)) {
+        600 - length_penalty
+    } else if name.contains(query) || label.contains(query) {
+        400 - length_penalty
+    } else if is_subsequence(query, name) {
+        200 - length_penalty
+    } else {
+        let longest = query.chars().count().max(name.chars().count()) as u32;
+        let distance = levenshtein(query, name) as u32;
+        100 * longest.saturating_sub(distance) / longest
+    }
+}
+
+/// Like `score`, for a normalized query and a city.
+pub(crate) fn city_score(query: &str, city: &City) -> u32 {
+    score(query, &normalize(&city.name), &normalize(&city.label()))
+}
+
+/// Sorts the cities by how well they match the query, best match first.
+/// Cities with the same score keep the order the server returned them in.
+pub fn rank_cities(query: &str, cities: &mut [City]) {
+    let query = normalize(query);
+    cities.sort_by_cached_key(|city| std::cmp::Reverse(city_score(&query, city)));
+}
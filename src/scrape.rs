@@ -0,0 +1,129 @@
+use select::{
+    document::Document,
+    node::Node,
+    predicate::{Attr, Class, Name, Predicate},
+};
+
+use crate::forecast::{DailyForecast, Forecast};
+
+use std::{error::Error, fmt::Display};
+
+/// Pages that came back fine but lack what was asked of them, which usually
+/// means CPTEC changed its markup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrapeError {
+    MissingMeteogram,
+    MissingForecast,
+}
+
+impl Display for ScrapeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScrapeError::MissingMeteogram => write!(f, "Could not find meteogram URL"),
+            ScrapeError::MissingForecast => write!(f, "Could not find forecast"),
+        }
+    }
+}
+
+impl Error for ScrapeError {}
+
+pub fn scrape_meteogram_url(page_contents: &str) -> Option<String> {
+    let doc = Document::from(page_contents);
+
+    let selector = Name("div")
+        .and(Attr("id", "meteograma"))
+        .descendant(Name("img"));
+
+    let img = doc.find(selector).next();
+
+    img.and_then(|node| node.attr("src"))
+        .map(|url| url.to_owned())
+}
+
+/// Reads the "próximos dias" cards of the forecast page, one per day.
+pub fn scrape_forecast(page_contents: &str) -> Forecast {
+    let doc = Document::from(page_contents);
+
+    let selector = Class("proximos-dias").descendant(Class("card"));
+    let days = doc.find(selector).map(scrape_day).collect();
+
+    Forecast { days }
+}
+
+fn scrape_day(card: Node) -> DailyForecast {
+    let text = |class| {
+        card.find(Class(class))
+            .next()
+            .map(|node| collapse_whitespace(&node.text()))
+            .filter(|text| !text.is_empty())
+    };
+
+    let condition = card
+        .find(Class("condicao"))
+        .next()
+        .and_then(|node| node.attr("title").or_else(|| node.attr("alt")))
+        .map(collapse_whitespace)
+        .or_else(|| text("condicao"))
+        .unwrap_or_default();
+
+    let humidity = text("umidade")
+        .map(|text| numbers(&text))
+        .unwrap_or_default();
+
+    DailyForecast {
+        date: text("data").unwrap_or_default(),
+        condition,
+        min_temperature: text("temp-min")
+            .and_then(|t| first_number(&t))
+            .map(|n| n as i32),
+        max_temperature: text("temp-max")
+            .and_then(|t| first_number(&t))
+            .map(|n| n as i32),
+        rain_probability: text("prob-chuva")
+            .and_then(|t| first_number(&t))
+            .map(|n| n as u8),
+        min_humidity: humidity.first().map(|&n| n as u8),
+        max_humidity: humidity.last().map(|&n| n as u8),
+        wind: text("vento"),
+        uv_index: text("uv").and_then(|t| first_number(&t)).map(|n| n as f32),
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Extracts every number in the text, so "60%-95%" yields 60 and 95 while
+/// "-2°" yields -2. Decimal commas are accepted.
+fn numbers(text: &str) -> Vec<f64> {
+    let chars: Vec<char> = text.chars().collect();
+    let digit_at = |i: usize| chars.get(i).is_some_and(char::is_ascii_digit);
+
+    let mut numbers = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let negative =
+            chars[i] == '-' && digit_at(i + 1) && (i == 0 || chars[i - 1].is_whitespace());
+        if !digit_at(i) && !negative {
+            i += 1;
+            continue;
+        }
+
+        let mut number = String::from(chars[i]);
+        i += 1;
+
+        while digit_at(i) || (matches!(chars.get(i), Some('.' | ',')) && digit_at(i + 1)) {
+            number.push(if chars[i] == ',' { '.' } else { chars[i] });
+            i += 1;
+        }
+
+        numbers.extend(number.parse::<f64>().ok());
+    }
+
+    numbers
+}
+
+fn first_number(text: &str) -> Option<f64> {
+    numbers(text).first().copied()
+}
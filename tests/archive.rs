@@ -0,0 +1,92 @@
+mod common;
+
+use chrono::{DateTime, Utc};
+use common::{temp_dir, METEOGRAM};
+use meteo::{Archive, City, RawCity};
+use reqwest::Url;
+
+use std::{convert::TryFrom, fs};
+
+fn city(id: &str, label: &str, custom: &str) -> City {
+    City::try_from(RawCity {
+        id: id.to_owned(),
+        label: label.to_owned(),
+        value: String::new(),
+        custom: custom.to_owned(),
+    })
+    .unwrap()
+}
+
+#[test]
+fn records_entries_and_deduplicates_images() {
+    let dir = temp_dir("archive");
+    let archive = Archive::open(&dir).unwrap();
+    let url = Url::parse("http://localhost/meteograma.png").unwrap();
+    let florianopolis = city("4564", "Florianópolis/SC", "sc/florianopolis");
+    let sao_jose = city("5012", "São José/SC", "sc/sao-jose");
+
+    let first: DateTime<Utc> = "2021-12-10T09:00:00Z".parse().unwrap();
+    let second: DateTime<Utc> = "2021-12-10T09:30:00Z".parse().unwrap();
+    archive
+        .record(&florianopolis, &url, METEOGRAM, first)
+        .unwrap();
+    archive.record(&sao_jose, &url, b"outra", first).unwrap();
+    let entry = archive
+        .record(&florianopolis, &url, METEOGRAM, second)
+        .unwrap();
+
+    assert_eq!(entry.id, 3);
+    assert_eq!(entry.url, url.as_str());
+    assert_eq!(archive.entry(3).unwrap(), Some(entry.clone()));
+    assert_eq!(archive.image(&entry).unwrap(), METEOGRAM);
+
+    let history = archive.history(&florianopolis).unwrap();
+    let ids: Vec<_> = history.iter().map(|entry| entry.id).collect();
+    assert_eq!(ids, [1, 3]);
+    assert_eq!(history[0].sha256, history[1].sha256);
+    assert_eq!(history[0].fetched_at, first);
+
+    assert_eq!(fs::read_dir(dir.join("images")).unwrap().count(), 2);
+}
+
+#[test]
+fn empty_archive_has_no_entries() {
+    let archive = Archive::open(temp_dir("archive-empty")).unwrap();
+
+    assert!(archive.entries().unwrap().is_empty());
+    assert_eq!(archive.entry(1).unwrap(), None);
+}
+
+#[test]
+fn cut_short_line_does_not_shift_ids() {
+    let dir = temp_dir("archive-cut-short");
+    let archive = Archive::open(&dir).unwrap();
+    let url = Url::parse("http://localhost/meteograma.png").unwrap();
+    let florianopolis = city("4564", "Florianópolis/SC", "sc/florianopolis");
+    let fetched_at: DateTime<Utc> = "2021-12-10T09:00:00Z".parse().unwrap();
+
+    archive
+        .record(&florianopolis, &url, METEOGRAM, fetched_at)
+        .unwrap();
+    let mut history = fs::OpenOptions::new()
+        .append(true)
+        .open(dir.join("history.jsonl"))
+        .unwrap();
+    std::io::Write::write_all(&mut history, b"{\"city\":").unwrap();
+
+    let entry = archive
+        .record(&florianopolis, &url, METEOGRAM, fetched_at)
+        .unwrap();
+
+    assert_eq!(entry.id, 3);
+    let lines = fs::read_to_string(dir.join("history.jsonl")).unwrap();
+    assert_eq!(lines.lines().count(), 3);
+    let ids: Vec<_> = archive
+        .entries()
+        .unwrap()
+        .iter()
+        .map(|entry| entry.id)
+        .collect();
+    assert_eq!(ids, [1, 3]);
+    assert_eq!(archive.entry(3).unwrap(), Some(entry));
+}
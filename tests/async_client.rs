@@ -0,0 +1,24 @@
+mod common;
+
+use common::{MockServer, METEOGRAM};
+use meteo::AsyncCptecClient;
+
+#[tokio::test]
+async fn search_and_download_meteogram() {
+    let server = MockServer::cptec();
+    let client = AsyncCptecClient::with_base_url(&server.url).unwrap();
+
+    let cities = client.search("florianopolis").await.unwrap();
+    let meteogram = client.meteogram(&cities[0]).await.unwrap();
+
+    assert_eq!(cities[0].to_string(), "Florianópolis/SC");
+    assert_eq!(meteogram, METEOGRAM);
+    assert_eq!(
+        server.requests(),
+        [
+            "/autocomplete?term=florianopolis",
+            "/sc/florianopolis",
+            "/meteogramas/4564.png"
+        ]
+    );
+}
@@ -0,0 +1,91 @@
+#![cfg(feature = "blocking")]
+
+mod common;
+
+use common::{temp_dir, MockServer, METEOGRAM};
+use meteo::{Cache, CacheMode, CptecClient, Resource};
+
+use std::time::Duration;
+
+#[test]
+fn fresh_entries_are_served_from_disk() {
+    let server = MockServer::cptec();
+    let cache = Cache::open(temp_dir("cache-fresh"), CacheMode::Use).unwrap();
+    let client = CptecClient::with_base_url(&server.url)
+        .unwrap()
+        .with_cache(cache);
+
+    let first = client.search("florianopolis").unwrap();
+    let second = client.search("florianopolis").unwrap();
+
+    assert_eq!(first.len(), second.len());
+    assert_eq!(server.requests(), ["/autocomplete?term=florianopolis"]);
+}
+
+#[test]
+fn stale_entries_are_revalidated() {
+    let server = MockServer::cptec_with_etags();
+    let mut cache = Cache::open(temp_dir("cache-stale"), CacheMode::Use).unwrap();
+    cache.set_ttl(Resource::Meteogram, Duration::ZERO);
+    let client = CptecClient::with_base_url(&server.url)
+        .unwrap()
+        .with_cache(cache);
+
+    let city = &client.search("florianopolis").unwrap()[0];
+    client.meteogram(city).unwrap();
+    let meteogram = client.meteogram(city).unwrap();
+
+    assert_eq!(meteogram, METEOGRAM);
+    assert_eq!(
+        server.requests(),
+        [
+            "/autocomplete?term=florianopolis",
+            "/sc/florianopolis",
+            "/meteogramas/4564.png",
+            "/meteogramas/4564.png",
+        ]
+    );
+}
+
+#[test]
+fn refresh_bypasses_stored_entries() {
+    let server = MockServer::cptec();
+    let dir = temp_dir("cache-refresh");
+
+    let client = CptecClient::with_base_url(&server.url).unwrap();
+    let cached = client
+        .clone()
+        .with_cache(Cache::open(&dir, CacheMode::Use).unwrap());
+    let refreshing = client.with_cache(Cache::open(&dir, CacheMode::Refresh).unwrap());
+
+    cached.search("florianopolis").unwrap();
+    refreshing.search("florianopolis").unwrap();
+    cached.search("florianopolis").unwrap();
+
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn entries_are_single_files_written_whole() {
+    let server = MockServer::cptec();
+    let dir = temp_dir("cache-files");
+    let cache = Cache::open(&dir, CacheMode::Use).unwrap();
+    let client = CptecClient::with_base_url(&server.url)
+        .unwrap()
+        .with_cache(cache);
+
+    let city = &client.search("florianopolis").unwrap()[0];
+    client.meteogram(city).unwrap();
+
+    let files: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(files.len(), 3);
+    assert!(files
+        .iter()
+        .all(|file| file.extension().unwrap() == "entry"));
+    assert!(files
+        .iter()
+        .any(|file| std::fs::read(file).unwrap().ends_with(METEOGRAM)));
+}
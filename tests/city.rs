@@ -0,0 +1,76 @@
+use meteo::{City, CityError, RawCity};
+
+use std::convert::TryFrom;
+
+fn raw(id: &str, label: &str, custom: &str) -> RawCity {
+    RawCity {
+        id: id.to_owned(),
+        label: label.to_owned(),
+        value: String::new(),
+        custom: custom.to_owned(),
+    }
+}
+
+#[test]
+fn decodes_name_state_and_path() {
+    let city = City::try_from(raw("4564", "Florian%C3%B3polis%2FSC", "sc/florianopolis")).unwrap();
+
+    assert_eq!(city.id, 4564);
+    assert_eq!(city.name, "Florianópolis");
+    assert_eq!(city.uf, "SC");
+    assert_eq!(city.path, ["sc", "florianopolis"]);
+    assert_eq!(city.to_string(), "Florianópolis/SC");
+    assert_eq!(city.custom(), "sc/florianopolis");
+}
+
+#[test]
+fn accepts_dash_separated_state() {
+    let city = City::try_from(raw("1", "Santa+Rita+-+pb", "/pb/santa-rita/")).unwrap();
+
+    assert_eq!(city.name, "Santa Rita");
+    assert_eq!(city.uf, "PB");
+    assert_eq!(city.path, ["pb", "santa-rita"]);
+}
+
+#[test]
+fn rejects_unexpected_shapes() {
+    assert_eq!(
+        City::try_from(raw("abc", "Lages%2FSC", "sc/lages")),
+        Err(CityError::InvalidId("abc".to_owned()))
+    );
+    assert_eq!(
+        City::try_from(raw("1", "Lages", "sc/lages")),
+        Err(CityError::MissingUf("Lages".to_owned()))
+    );
+    assert_eq!(
+        City::try_from(raw("1", "Lages%2FSC", "")),
+        Err(CityError::EmptyPath("Lages/SC".to_owned()))
+    );
+}
+
+#[test]
+fn round_trips_through_raw_city() {
+    let city = City::try_from(raw("5012", "S%C3%A3o+Jos%C3%A9%2FSC", "sc/sao-jose")).unwrap();
+
+    assert_eq!(City::try_from(RawCity::from(&city)), Ok(city));
+}
+
+#[test]
+fn round_trips_through_json() {
+    let city = City::try_from(raw("5012", "S%C3%A3o+Jos%C3%A9%2FSC", "sc/sao-jose")).unwrap();
+
+    let json = serde_json::to_string(&city).unwrap();
+
+    assert_eq!(
+        json,
+        r#"{"id":5012,"name":"São José","uf":"SC","custom":"sc/sao-jose"}"#
+    );
+    assert_eq!(serde_json::from_str::<City>(&json).unwrap(), city);
+}
+
+#[test]
+fn rejects_decoded_city_without_path() {
+    let json = r#"{"id":5012,"name":"São José","uf":"SC","custom":""}"#;
+
+    assert!(serde_json::from_str::<City>(json).is_err());
+}
@@ -0,0 +1,818 @@
+#![cfg(all(feature = "blocking", feature = "cli"))]
+
+mod common;
+
+use common::{temp_dir, MockServer, METEOGRAM};
+
+use std::{
+    fs,
+    io::{BufRead, BufReader},
+    path::Path,
+    process::{Child, Command, Output, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+fn meteo_command(server: &MockServer, config: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_meteo"));
+    command
+        .args(["--no-cache", "--base-url", &server.url])
+        .args(args)
+        .env("METEO_CONFIG", config)
+        .env("METEO_CITY_INDEX", config.with_extension("json"))
+        .env("METEO_ARCHIVE", config.with_extension("archive"))
+        .env("METEO_GAZETTEER", config.with_extension("gazetteer.json"))
+        .env("METEO_ALERT_STATE", config.with_extension("forecasts"))
+        .stdin(Stdio::null());
+    command
+}
+
+fn meteo_with_config(server: &MockServer, config: &Path, args: &[&str]) -> Output {
+    meteo_command(server, config, args).output().unwrap()
+}
+
+/// Runs without a config, in a directory of its own so that tests running in
+/// parallel don't share an archive or city index.
+fn meteo(server: &MockServer, args: &[&str]) -> Output {
+    static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+    let run = RUNS.fetch_add(1, Ordering::Relaxed);
+    let missing = temp_dir(&format!("cli-run-{}", run)).join("missing-config.toml");
+    meteo_with_config(server, &missing, args)
+}
+
+#[test]
+fn saves_selected_meteogram() {
+    let server = MockServer::cptec();
+    let output = temp_dir("cli-index").join("meteo.png");
+
+    let result = meteo(
+        &server,
+        &[
+            "--index",
+            "0",
+            "-o",
+            output.to_str().unwrap(),
+            "florianopolis",
+        ],
+    );
+
+    assert!(result.status.success());
+    assert_eq!(fs::read(&output).unwrap(), METEOGRAM);
+}
+
+#[test]
+fn selects_by_exact_label_and_id() {
+    let server = MockServer::cptec();
+    let dir = temp_dir("cli-exact");
+
+    for (flags, file) in [(["--exact"], "exact.png"), (["--id=4564"], "id.png")] {
+        let output = dir.join(file);
+        let mut args = flags.to_vec();
+        args.extend(["-o", output.to_str().unwrap(), "FLORIANOPOLIS"]);
+
+        let result = meteo(&server, &args);
+
+        assert!(result.status.success(), "{:?}", result);
+        assert_eq!(fs::read(&output).unwrap(), METEOGRAM);
+    }
+}
+
+#[test]
+fn ambiguous_selection_without_tty_fails() {
+    let server = MockServer::cptec();
+
+    let result = meteo(&server, &["florianopolis"]);
+    let stderr = String::from_utf8_lossy(&result.stderr);
+
+    assert!(!result.status.success());
+    assert!(stderr.contains("2 cities match"), "{}", stderr);
+}
+
+#[test]
+fn empty_search_has_distinct_exit_code() {
+    let server = MockServer::start(vec![(
+        "/autocomplete",
+        common::Response::ok("application/json", "[]"),
+    )]);
+
+    let result = meteo(&server, &["atlantida"]);
+
+    assert_eq!(result.status.code(), Some(3));
+    assert_eq!(
+        String::from_utf8_lossy(&result.stderr).trim(),
+        "nenhuma cidade encontrada"
+    );
+}
+
+#[test]
+fn renders_meteogram_in_terminal() {
+    let server = MockServer::cptec();
+
+    let blocks = meteo(
+        &server,
+        &[
+            "--first",
+            "--terminal",
+            "--protocol",
+            "blocks",
+            "florianopolis",
+        ],
+    );
+    let sixel = meteo(
+        &server,
+        &[
+            "--first",
+            "--terminal",
+            "--protocol",
+            "sixel",
+            "florianopolis",
+        ],
+    );
+
+    let blocks = String::from_utf8(blocks.stdout).unwrap();
+    assert_eq!(blocks.lines().filter(|line| line.contains('▀')).count(), 3);
+    assert!(blocks.starts_with("\x1b[38;2;0;0;200m\x1b[48;2;0;40;200m▀"));
+
+    let sixel = String::from_utf8(sixel.stdout).unwrap();
+    assert!(sixel.starts_with("\x1bPq\"1;1;8;6#0;2;0;0;0"));
+    assert!(sixel.trim_end().ends_with("-\x1b\\"));
+}
+
+#[test]
+fn prints_forecast_table() {
+    let server = MockServer::cptec();
+
+    let result = meteo(&server, &["forecast", "--first", "florianopolis"]);
+    let stdout = String::from_utf8(result.stdout).unwrap();
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        [
+            "Data       Condição              Mín   Máx   Chuva  Umidade  Vento        UV",
+            "Seg 18/10  Chuva isolada         19°C  28°C  70%    60-95%   NE Moderado  11",
+            "Ter 19/10  Parcialmente nublado  -2°C  25°C  10%    55%      -            -",
+        ]
+    );
+}
+
+#[test]
+fn lists_cities_as_json_and_csv() {
+    let server = MockServer::cptec();
+
+    let json = meteo(&server, &["search", "--format", "json", "florianopolis"]);
+    let csv = meteo(&server, &["search", "--format", "csv", "florianopolis"]);
+
+    let json: serde_json::Value = serde_json::from_slice(&json.stdout).unwrap();
+    assert_eq!(json[1]["name"], "São José");
+    assert_eq!(json[1]["uf"], "SC");
+    assert_eq!(json[1]["id"], 5012);
+
+    assert_eq!(
+        String::from_utf8(csv.stdout).unwrap(),
+        "id,name,uf,custom\n\
+         4564,Florianópolis,SC,sc/florianopolis\n\
+         5012,São José,SC,sc/sao-jose\n"
+    );
+}
+
+#[test]
+fn prints_forecast_as_csv() {
+    let server = MockServer::cptec();
+
+    let result = meteo(
+        &server,
+        &["forecast", "--first", "--format", "csv", "florianopolis"],
+    );
+    let stdout = String::from_utf8(result.stdout).unwrap();
+
+    assert_eq!(
+        stdout.lines().take(2).collect::<Vec<_>>(),
+        [
+            "date,condition,min_temperature,max_temperature,rain_probability,min_humidity,max_humidity,wind,uv_index",
+            "Seg 18/10,Chuva isolada,19,28,70,60,95,NE Moderado,11.0",
+        ]
+    );
+}
+
+#[test]
+fn meteogram_subcommand_matches_default_command() {
+    let server = MockServer::cptec();
+    let output = temp_dir("cli-subcommand").join("meteo.png");
+
+    let result = meteo(
+        &server,
+        &[
+            "meteogram",
+            "--first",
+            "-o",
+            output.to_str().unwrap(),
+            "florianopolis",
+        ],
+    );
+
+    assert!(result.status.success());
+    assert_eq!(fs::read(&output).unwrap(), METEOGRAM);
+}
+
+#[test]
+fn config_shows_base_url() {
+    let server = MockServer::cptec();
+
+    let result = meteo(&server, &["config"]);
+    let stdout = String::from_utf8(result.stdout).unwrap();
+
+    assert!(stdout.contains(&format!("base_url = {}", server.url)));
+    assert!(stdout.contains("cache_dir = (disabled)"));
+    assert!(server.requests().is_empty());
+}
+
+#[test]
+fn favourites_resolve_without_searching() {
+    let server = MockServer::cptec();
+    let dir = temp_dir("cli-fav");
+    let config = dir.join("config.toml");
+    let output = dir.join("meteo.png");
+
+    let add = meteo_with_config(
+        &server,
+        &config,
+        &["fav", "add", "floripa", "--first", "florianopolis"],
+    );
+    assert!(add.status.success(), "{:?}", add);
+
+    let default = meteo_with_config(&server, &config, &["-o", output.to_str().unwrap()]);
+    assert!(default.status.success(), "{:?}", default);
+    assert_eq!(fs::read(&output).unwrap(), METEOGRAM);
+
+    let list = meteo_with_config(&server, &config, &["fav", "list"]);
+    assert_eq!(
+        String::from_utf8(list.stdout).unwrap(),
+        "floripa -> Florianópolis/SC (padrão)\n"
+    );
+
+    let forecast = meteo_with_config(&server, &config, &["forecast", "floripa"]);
+    assert!(forecast.status.success(), "{:?}", forecast);
+    assert_eq!(
+        server
+            .requests()
+            .iter()
+            .filter(|r| r.starts_with("/autocomplete"))
+            .count(),
+        1
+    );
+
+    let remove = meteo_with_config(&server, &config, &["fav", "remove", "floripa"]);
+    assert!(remove.status.success(), "{:?}", remove);
+    assert_eq!(fs::read_to_string(&config).unwrap(), "[favourites]\n");
+}
+
+#[test]
+fn missing_default_favourite_is_an_error() {
+    let server = MockServer::cptec();
+
+    let result = meteo(&server, &[]);
+    let stderr = String::from_utf8_lossy(&result.stderr);
+
+    assert!(!result.status.success());
+    assert!(stderr.contains("no default favourite"), "{}", stderr);
+}
+
+#[test]
+fn filters_search_by_uf() {
+    let server = MockServer::start(vec![(
+        "/autocomplete",
+        common::Response::ok("application/json", common::SAO_JOSE_JSON),
+    )]);
+
+    let result = meteo(&server, &["search", "--uf", "sc", "--uf", "PR", "sao jose"]);
+
+    assert_eq!(
+        String::from_utf8(result.stdout).unwrap(),
+        "#  Cidade                UF  Id\n\
+         0  São José              SC  5012\n\
+         1  São José dos Pinhais  PR  5014\n"
+    );
+}
+
+#[test]
+fn offline_search_requires_an_index() {
+    let server = MockServer::cptec();
+    let dir = temp_dir("cli-offline");
+    let config = dir.join("config.toml");
+
+    let missing = meteo_with_config(&server, &config, &["--offline", "search", "floripa"]);
+    assert!(!missing.status.success());
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("meteo index update"));
+
+    let cities = serde_json::from_str(common::CITIES_JSON).unwrap();
+    meteo::CityIndex::new(cities)
+        .save(&config.with_extension("json"))
+        .unwrap();
+
+    let found = meteo_with_config(&server, &config, &["--offline", "search", "florianopolis"]);
+    assert!(found.status.success(), "{:?}", found);
+    assert!(String::from_utf8(found.stdout)
+        .unwrap()
+        .contains("Florianópolis"));
+    assert!(server.requests().is_empty());
+}
+
+#[test]
+fn picks_nearest_city_by_coordinates() {
+    let server = MockServer::cptec();
+    let dir = temp_dir("cli-nearest");
+    let config = dir.join("config.toml");
+    let output = dir.join("meteo.png");
+
+    let cities = serde_json::from_str(common::CITIES_JSON).unwrap();
+    meteo::CityIndex::new(cities)
+        .save(&config.with_extension("json"))
+        .unwrap();
+
+    let csv = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/municipios.csv");
+    let import = meteo_with_config(&server, &config, &["index", "gazetteer", csv]);
+    assert!(import.status.success(), "{:?}", import);
+    assert!(config.with_extension("gazetteer.json").exists());
+
+    let result = meteo_with_config(
+        &server,
+        &config,
+        &[
+            "--lat",
+            "-27.59",
+            "--lon",
+            "-48.55",
+            "-o",
+            output.to_str().unwrap(),
+        ],
+    );
+
+    assert!(result.status.success(), "{:?}", result);
+    assert!(String::from_utf8_lossy(&result.stderr).starts_with("Florianópolis/SC"));
+    assert_eq!(fs::read(&output).unwrap(), METEOGRAM);
+}
+
+#[test]
+fn batch_fetches_every_city_and_reports_failures() {
+    let server = MockServer::cptec();
+    let dir = temp_dir("cli-batch");
+    let list = dir.join("cidades.txt");
+    let out_dir = dir.join("meteogramas");
+    fs::write(&list, "# cidades\nflorianopolis\n\nsao jose\n").unwrap();
+
+    let result = meteo(
+        &server,
+        &[
+            "batch",
+            list.to_str().unwrap(),
+            "--out-dir",
+            out_dir.to_str().unwrap(),
+            "--name",
+            "{city}-{uf}-{id}.{ext}",
+        ],
+    );
+    let stdout = String::from_utf8(result.stdout).unwrap();
+
+    assert!(!result.status.success());
+    assert!(stdout.contains("1 baixados, 1 falharam"), "{}", stdout);
+    assert_eq!(
+        fs::read(out_dir.join("florianopolis-SC-4564.png")).unwrap(),
+        METEOGRAM
+    );
+    assert_eq!(fs::read_dir(&out_dir).unwrap().count(), 1);
+}
+
+#[test]
+fn batch_reads_toml_lists() {
+    let server = MockServer::cptec();
+    let dir = temp_dir("cli-batch-toml");
+    let list = dir.join("cidades.toml");
+    fs::write(&list, "cities = [\"florianopolis\"]\n").unwrap();
+
+    let result = meteo(
+        &server,
+        &[
+            "batch",
+            list.to_str().unwrap(),
+            "--out-dir",
+            dir.to_str().unwrap(),
+        ],
+    );
+
+    assert!(result.status.success(), "{:?}", result);
+    let date = chrono::Local::now().format("%Y-%m-%d");
+    assert!(dir.join(format!("florianopolis-SC-{}.png", date)).exists());
+}
+
+#[test]
+fn output_templates_and_directories() {
+    let server = MockServer::cptec();
+    let dir = temp_dir("cli-output-template");
+
+    let template = format!("{}/{{uf}}/{{city}}-{{id}}.{{ext}}", dir.display());
+    let result = meteo(&server, &["--first", "-o", &template, "florianopolis"]);
+    assert!(result.status.success(), "{:?}", result);
+    assert_eq!(
+        fs::read(dir.join("SC/florianopolis-4564.png")).unwrap(),
+        METEOGRAM
+    );
+
+    let result = meteo(
+        &server,
+        &["--first", "-o", dir.to_str().unwrap(), "florianopolis"],
+    );
+    assert!(result.status.success(), "{:?}", result);
+    let date = chrono::Local::now().format("%Y-%m-%d");
+    assert!(dir.join(format!("florianopolis-SC-{}.png", date)).exists());
+}
+
+#[test]
+fn existing_files_need_force_or_no_clobber() {
+    let server = MockServer::cptec();
+    let output = temp_dir("cli-overwrite").join("meteo.png");
+    let output = output.to_str().unwrap();
+    fs::write(output, "antigo").unwrap();
+
+    let result = meteo(&server, &["--first", "-o", output, "florianopolis"]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("--force"));
+    assert_eq!(fs::read(output).unwrap(), b"antigo");
+
+    let args = ["--first", "--no-clobber", "-o", output, "florianopolis"];
+    let result = meteo(&server, &args);
+    assert!(result.status.success(), "{:?}", result);
+    assert_eq!(fs::read(output).unwrap(), b"antigo");
+
+    let args = ["--first", "--force", "-o", output, "florianopolis"];
+    let result = meteo(&server, &args);
+    assert!(result.status.success(), "{:?}", result);
+    assert_eq!(fs::read(output).unwrap(), METEOGRAM);
+
+    let parent = Path::new(output).parent().unwrap();
+    assert_eq!(fs::read_dir(parent).unwrap().count(), 1);
+}
+
+#[test]
+fn writes_meteogram_to_stdout() {
+    let server = MockServer::cptec();
+
+    let result = meteo(&server, &["--first", "-o", "-", "florianopolis"]);
+    assert!(result.status.success(), "{:?}", result);
+    assert_eq!(result.stdout, METEOGRAM);
+    assert!(!Path::new("-").exists());
+}
+
+#[test]
+fn archives_fetched_meteograms() {
+    let server = MockServer::cptec();
+    let dir = temp_dir("cli-history");
+    let config = dir.join("config.toml");
+
+    for _ in 0..2 {
+        let args = ["--first", "-o", "-", "florianopolis"];
+        let result = meteo_with_config(&server, &config, &args);
+        assert!(result.status.success(), "{:?}", result);
+    }
+
+    let result = meteo_with_config(&server, &config, &["history", "--first", "florianopolis"]);
+    let stdout = String::from_utf8(result.stdout).unwrap();
+    assert!(result.status.success(), "{}", stdout);
+    assert_eq!(stdout.lines().count(), 3, "{}", stdout);
+    assert!(
+        stdout.lines().nth(2).unwrap().starts_with("2   "),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("Florianópolis/SC"));
+
+    let result = meteo_with_config(&server, &config, &["history", "show", "2", "-o", "-"]);
+    assert!(result.status.success(), "{:?}", result);
+    assert_eq!(result.stdout, METEOGRAM);
+
+    let images = dir.join("config.archive").join("images");
+    assert_eq!(fs::read_dir(images).unwrap().count(), 1);
+}
+
+#[test]
+fn archive_errors_do_not_fail_the_fetch() {
+    let server = MockServer::cptec();
+    let dir = temp_dir("cli-history-broken");
+    let config = dir.join("config.toml");
+    fs::create_dir_all(dir.join("config.archive").join("history.jsonl")).unwrap();
+
+    let result = meteo_with_config(&server, &config, &["--first", "-o", "-", "florianopolis"]);
+
+    assert!(result.status.success(), "{:?}", result);
+    assert_eq!(result.stdout, METEOGRAM);
+    let stderr = String::from_utf8(result.stderr).unwrap();
+    assert!(
+        stderr.contains("Could not archive the meteogram"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn watch_keeps_the_output_file_up_to_date() {
+    let server = MockServer::cptec();
+    let dir = temp_dir("cli-watch");
+    let output = dir.join("meteo.png");
+
+    let args = [
+        "watch",
+        "--first",
+        "--every",
+        "0s",
+        "--count",
+        "2",
+        "-o",
+        output.to_str().unwrap(),
+        "florianopolis",
+    ];
+    let result = meteo_command(&server, &dir.join("config.toml"), &args)
+        .env("METEO_MIN_INTERVAL", "0s")
+        .output()
+        .unwrap();
+
+    assert!(result.status.success(), "{:?}", result);
+    assert_eq!(fs::read(&output).unwrap(), METEOGRAM);
+    // The image did not change between the two rounds.
+    assert!(!String::from_utf8_lossy(&result.stderr).contains("atualizado"));
+    let meteograms = server
+        .requests()
+        .iter()
+        .filter(|r| r.ends_with(".png"))
+        .count();
+    assert_eq!(meteograms, 2);
+}
+
+#[test]
+fn watch_rejects_invalid_intervals() {
+    let server = MockServer::cptec();
+
+    for interval in [
+        "30x",
+        "0s",
+        "30s",
+        "9999999999999999h",
+        "99999999999999999999",
+    ] {
+        let result = meteo(&server, &["watch", "--every", interval, "florianopolis"]);
+
+        assert!(!result.status.success());
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        assert!(stderr.contains(interval), "{}", stderr);
+        assert!(!stderr.contains("panicked"), "{}", stderr);
+    }
+    assert!(server.requests().is_empty());
+}
+
+#[test]
+fn alerts_when_rain_rises_above_threshold() {
+    let server = MockServer::cptec();
+    let config = temp_dir("cli-alert").join("config.toml");
+    let args = ["alert", "--first", "--rain-above", "60%", "florianopolis"];
+
+    let result = meteo_with_config(&server, &config, &args);
+    let stdout = String::from_utf8(result.stdout).unwrap();
+    assert_eq!(result.status.code(), Some(4), "{}", stdout);
+    assert_eq!(
+        stdout,
+        "Florianópolis/SC Seg 18/10: chuva 70% (antes -), acima de 60%\n"
+    );
+    assert!(config
+        .with_extension("forecasts")
+        .join("4564.json")
+        .exists());
+
+    let result = meteo_with_config(&server, &config, &args);
+    assert!(result.status.success(), "{:?}", result);
+    assert!(result.stdout.is_empty());
+}
+
+/// Starts `meteo serve` on a free port and returns it along with its URL.
+fn serve(server: &MockServer, config: &Path) -> (Child, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_meteo"))
+        .args(["--no-cache", "--base-url", &server.url])
+        .args(["serve", "--bind", "127.0.0.1:0"])
+        .env("METEO_CONFIG", config)
+        .env("METEO_CITY_INDEX", config.with_extension("json"))
+        .env("METEO_ARCHIVE", config.with_extension("archive"))
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut line = String::new();
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    stderr.read_line(&mut line).unwrap();
+    let url = line.trim().rsplit(' ').next().unwrap().to_owned();
+
+    (child, url)
+}
+
+#[test]
+fn serves_cities_meteograms_and_forecasts() {
+    let server = MockServer::cptec();
+    let config = temp_dir("cli-serve").join("config.toml");
+
+    let (mut child, url) = serve(&server, &config);
+
+    let get = |path: &str| reqwest::blocking::get(format!("{}{}", url, path)).unwrap();
+
+    let response = get("/city/4564/forecast.json");
+    assert_eq!(response.status(), 404);
+    assert_eq!(response.headers()["cache-control"], "no-store");
+
+    let response = get("/cities?q=florianopolis");
+    assert_eq!(response.status(), 200);
+    let cities: serde_json::Value = serde_json::from_slice(&response.bytes().unwrap()).unwrap();
+    assert_eq!(cities[0]["id"], 4564);
+
+    let response = get("/city/4564/meteogram.png");
+    assert_eq!(response.headers()["content-type"], "image/png");
+    assert_eq!(response.headers()["cache-control"], "public, max-age=1800");
+    assert_eq!(response.bytes().unwrap(), METEOGRAM);
+
+    let response = get("/city/4564/forecast.json");
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("application/json"));
+    let forecast: serde_json::Value = serde_json::from_slice(&response.bytes().unwrap()).unwrap();
+    assert_eq!(forecast["days"][0]["rain_probability"], 70);
+
+    // Served from memory the second time.
+    get("/city/4564/meteogram.png");
+    let meteograms = server
+        .requests()
+        .iter()
+        .filter(|r| r.ends_with(".png"))
+        .count();
+    assert_eq!(meteograms, 1);
+
+    let response = get("/metrics");
+    assert_eq!(response.headers()["cache-control"], "no-store");
+    let metrics = response.text().unwrap();
+    let labels = r#"id="4564",city="Florianópolis",uf="SC""#;
+    assert!(metrics.contains(&format!(
+        "meteo_temperature_max_celsius{{{},day=\"0\"}} 28\n",
+        labels
+    )));
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn serves_favourites_and_caches_equivalent_searches_once() {
+    let server = MockServer::cptec();
+    let config = temp_dir("cli-serve-favourites").join("config.toml");
+    let add = meteo_with_config(
+        &server,
+        &config,
+        &["fav", "add", "floripa", "--first", "florianopolis"],
+    );
+    assert!(add.status.success(), "{:?}", add);
+
+    let (mut child, url) = serve(&server, &config);
+    let get = |path: &str| reqwest::blocking::get(format!("{}{}", url, path)).unwrap();
+
+    let response = get("/city/4564/forecast.json");
+    assert_eq!(response.status(), 200);
+
+    for query in ["Florian%C3%B3polis", "florianopolis", "++FLORIANOPOLIS"] {
+        let response = get(&format!("/cities/?q={}&page=1", query));
+        assert_eq!(response.status(), 200);
+    }
+    let searches = server
+        .requests()
+        .iter()
+        .filter(|r| r.starts_with("/autocomplete"))
+        .count();
+    assert_eq!(searches, 2);
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn exports_forecast_metrics() {
+    let server = MockServer::cptec();
+
+    let result = meteo(&server, &["export-metrics", "florianopolis"]);
+    let stdout = String::from_utf8(result.stdout).unwrap();
+
+    assert!(result.status.success(), "{}", stdout);
+    let labels = r#"id="4564",city="Florianópolis",uf="SC""#;
+    assert!(stdout.contains("# TYPE meteo_rain_probability_percent gauge\n"));
+    assert!(stdout.contains(&format!(
+        "meteo_rain_probability_percent{{{},day=\"0\"}} 70\n",
+        labels
+    )));
+    assert!(stdout.contains(&format!(
+        "meteo_temperature_min_celsius{{{},day=\"1\"}} -2\n",
+        labels
+    )));
+    assert!(stdout.contains(&format!(
+        "meteo_forecast_day_info{{{},day=\"1\",date=\"Ter 19/10\"}} 1\n",
+        labels
+    )));
+    assert!(!stdout
+        .lines()
+        .any(|line| line.starts_with("meteo_temperature") && line.contains("date=")));
+    assert!(stdout.contains(&format!("meteo_fetch_errors_total{{{}}} 0\n", labels)));
+}
+
+#[test]
+fn counts_missing_meteograms_apart_from_other_errors() {
+    let page = common::FORECAST_PAGE.replace("meteograma", "grafico");
+    let server = MockServer::start(vec![
+        (
+            "/autocomplete",
+            common::Response::ok("application/json", common::CITIES_JSON),
+        ),
+        ("/sc/florianopolis", common::Response::ok("text/html", page)),
+    ]);
+
+    let result = meteo(&server, &["export-metrics", "florianopolis", "sao jose"]);
+    let stdout = String::from_utf8(result.stdout).unwrap();
+
+    assert!(result.status.success(), "{}", stdout);
+    let florianopolis = r#"id="4564",city="Florianópolis",uf="SC""#;
+    let sao_jose = r#"id="5012",city="São José",uf="SC""#;
+    assert!(stdout.contains(&format!(
+        "meteo_missing_meteogram_errors_total{{{}}} 1\n",
+        florianopolis
+    )));
+    assert!(stdout.contains(&format!(
+        "meteo_fetch_errors_total{{{}}} 0\n",
+        florianopolis
+    )));
+    assert!(stdout.contains(&format!("meteo_fetch_errors_total{{{}}} 1\n", sao_jose)));
+    let pages = server
+        .requests()
+        .iter()
+        .filter(|r| r.starts_with("/sc/"))
+        .count();
+    assert_eq!(pages, 2);
+}
+
+#[test]
+fn mqtt_dry_run_prints_discovery_and_state() {
+    let server = MockServer::cptec();
+
+    let args = ["mqtt", "--dry-run", "--count", "1", "florianopolis"];
+    let result = meteo(&server, &args);
+    let stdout = String::from_utf8(result.stdout).unwrap();
+    assert!(result.status.success(), "{}", stdout);
+
+    let messages: Vec<(&str, serde_json::Value)> = stdout
+        .lines()
+        .map(|line| {
+            let (topic, payload) = line.split_once(' ').unwrap();
+            (topic, serde_json::from_str(payload).unwrap())
+        })
+        .collect();
+    assert_eq!(messages.len(), 8);
+
+    let (topic, config) = &messages[0];
+    assert_eq!(
+        *topic,
+        "homeassistant/sensor/meteo_4564/max_temperature/config"
+    );
+    assert_eq!(config["state_topic"], "meteo/4564/state");
+    assert_eq!(config["unit_of_measurement"], "°C");
+    assert_eq!(config["unique_id"], "meteo_4564_max_temperature");
+    assert_eq!(config["device"]["name"], "Florianópolis/SC");
+
+    let (topic, state) = messages.last().unwrap();
+    assert_eq!(*topic, "meteo/4564/state");
+    assert_eq!(state["today"]["max_temperature"], 28);
+    assert_eq!(state["days"][1]["rain_probability"], 10);
+    assert_eq!(
+        state["meteogram_url"],
+        format!("{}/meteogramas/4564.png", server.url)
+    );
+    assert_eq!(
+        server
+            .requests()
+            .iter()
+            .filter(|r| r.starts_with("/sc/"))
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn mqtt_fails_when_the_broker_is_unreachable() {
+    let server = MockServer::cptec();
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let broker = listener.local_addr().unwrap().to_string();
+    drop(listener);
+
+    let args = ["mqtt", "--broker", &broker, "--count", "1", "florianopolis"];
+    let result = meteo(&server, &args);
+
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("Could not connect"));
+}
@@ -0,0 +1,130 @@
+#![cfg(feature = "blocking")]
+
+mod common;
+
+use common::{MockServer, METEOGRAM};
+use meteo::CptecClient;
+
+#[test]
+fn search_sends_term_to_autocomplete() {
+    let server = MockServer::cptec();
+    let client = CptecClient::with_base_url(&server.url).unwrap();
+
+    let cities = client.search("florianopolis").unwrap();
+
+    assert_eq!(cities.len(), 2);
+    assert_eq!(cities[0].to_string(), "Florianópolis/SC");
+    assert_eq!(server.requests(), ["/autocomplete?term=florianopolis"]);
+}
+
+#[test]
+fn meteogram_is_downloaded_from_base_url() {
+    let server = MockServer::cptec();
+    let client = CptecClient::with_base_url(&server.url).unwrap();
+
+    let cities = client.search("florianopolis").unwrap();
+    let meteogram = client.meteogram(&cities[0]).unwrap();
+
+    assert_eq!(meteogram, METEOGRAM);
+    assert_eq!(
+        server.requests()[1..],
+        ["/sc/florianopolis", "/meteogramas/4564.png"]
+    );
+}
+
+#[test]
+fn absolute_meteogram_url_is_rebased_onto_base_url() {
+    let server = MockServer::start(vec![
+        (
+            "/mirror/sc/florianopolis",
+            common::Response::ok("text/html", common::ABSOLUTE_METEOGRAM_PAGE),
+        ),
+        (
+            "/mirror/meteogramas/4564.png",
+            common::Response::ok("image/png", METEOGRAM),
+        ),
+    ]);
+    let client = CptecClient::with_base_url(&format!("{}/mirror", server.url)).unwrap();
+    let cities = serde_json::from_str::<Vec<meteo::City>>(common::CITIES_JSON).unwrap();
+
+    let meteogram = client.meteogram(&cities[0]).unwrap();
+
+    assert_eq!(meteogram, METEOGRAM);
+    assert_eq!(
+        server.requests(),
+        [
+            "/mirror/sc/florianopolis",
+            "/mirror/meteogramas/4564.png?v=2"
+        ]
+    );
+}
+
+#[test]
+fn meteogram_on_another_host_is_left_alone() {
+    let page = common::ABSOLUTE_METEOGRAM_PAGE.replace("tempo.cptec.inpe.br", "img.example.org");
+
+    for client in [
+        CptecClient::new(),
+        CptecClient::with_base_url("http://localhost:8080/mirror").unwrap(),
+    ] {
+        assert_eq!(
+            client.meteogram_url_from_page(&page).unwrap().as_str(),
+            "https://img.example.org/meteogramas/4564.png?v=2"
+        );
+    }
+}
+
+#[test]
+fn meteogram_on_cptec_is_kept_with_the_default_base_url() {
+    let client = CptecClient::new();
+
+    assert_eq!(
+        client
+            .meteogram_url_from_page(common::ABSOLUTE_METEOGRAM_PAGE)
+            .unwrap()
+            .as_str(),
+        "https://tempo.cptec.inpe.br/meteogramas/4564.png?v=2"
+    );
+}
+
+#[test]
+fn base_url_path_is_preserved() {
+    let client = CptecClient::with_base_url("http://localhost:8080/mirror").unwrap();
+    let cities = serde_json::from_str::<Vec<meteo::City>>(common::CITIES_JSON).unwrap();
+
+    assert_eq!(
+        client.forecast_url(&cities[0]).unwrap().as_str(),
+        "http://localhost:8080/mirror/sc/florianopolis"
+    );
+}
+
+#[test]
+fn missing_meteogram_is_an_error() {
+    let server = MockServer::start(vec![(
+        "/sc/florianopolis",
+        common::Response::ok("text/html", "<html></html>"),
+    )]);
+    let client = CptecClient::with_base_url(&server.url).unwrap();
+    let cities = serde_json::from_str::<Vec<meteo::City>>(common::CITIES_JSON).unwrap();
+
+    let error = client.meteogram(&cities[0]).unwrap_err();
+
+    assert_eq!(error.to_string(), "Could not find meteogram URL");
+    assert_eq!(
+        error.downcast_ref::<meteo::ScrapeError>(),
+        Some(&meteo::ScrapeError::MissingMeteogram)
+    );
+}
+
+#[test]
+fn changed_autocomplete_payload_is_an_error() {
+    let server = MockServer::start(vec![(
+        "/autocomplete",
+        common::Response::ok("application/json", r#"[{"id":"x","label":"Lages"}]"#),
+    )]);
+    let client = CptecClient::with_base_url(&server.url).unwrap();
+
+    let error = client.search("lages").unwrap_err();
+
+    assert_eq!(error.to_string(), "Unexpected autocomplete payload");
+}
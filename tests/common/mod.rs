@@ -0,0 +1,212 @@
+#![allow(dead_code)]
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+pub const CITIES_JSON: &str = r#"[
+    {"id":"4564","label":"Florian%C3%B3polis%2FSC","value":"Florianópolis/SC","custom":"sc/florianopolis"},
+    {"id":"5012","label":"S%C3%A3o+Jos%C3%A9%2FSC","value":"São José/SC","custom":"sc/sao-jose"}
+]"#;
+
+pub const SAO_JOSE_JSON: &str = r#"[
+    {"id":"5012","label":"S%C3%A3o+Jos%C3%A9%2FSC","value":"São José/SC","custom":"sc/sao-jose"},
+    {"id":"5013","label":"S%C3%A3o+Jos%C3%A9+do+Rio+Preto%2FSP","value":"São José do Rio Preto/SP","custom":"sp/sao-jose-do-rio-preto"},
+    {"id":"5014","label":"S%C3%A3o+Jos%C3%A9+dos+Pinhais%2FPR","value":"São José dos Pinhais/PR","custom":"pr/sao-jose-dos-pinhais"}
+]"#;
+
+pub const FORECAST_PAGE: &str = r#"<html><body>
+    <div class="proximos-dias">
+        <div class="card">
+            <div class="data">Seg 18/10</div>
+            <img class="condicao" src="/icones/ci.png" title="Chuva isolada">
+            <span class="temp-max">28°</span>
+            <span class="temp-min">19°</span>
+            <span class="prob-chuva">70%</span>
+            <span class="umidade">60% - 95%</span>
+            <span class="vento">NE  Moderado</span>
+            <span class="uv">11,0 Extremo</span>
+        </div>
+        <div class="card">
+            <div class="data">Ter 19/10</div>
+            <img class="condicao" src="/icones/pn.png" alt="Parcialmente nublado">
+            <span class="temp-max">25°</span>
+            <span class="temp-min">-2°</span>
+            <span class="prob-chuva">10%</span>
+            <span class="umidade">55%</span>
+        </div>
+    </div>
+    <div id="meteograma">
+        <img src="/meteogramas/4564.png" alt="Meteograma">
+    </div>
+</body></html>"#;
+
+/// Links the meteogram by absolute URL, the way the live CPTEC pages do.
+pub const ABSOLUTE_METEOGRAM_PAGE: &str = r#"<html><body>
+    <div id="meteograma">
+        <img src="https://tempo.cptec.inpe.br/meteogramas/4564.png?v=2" alt="Meteograma">
+    </div>
+</body></html>"#;
+
+pub const METEOGRAM: &[u8] = include_bytes!("../fixtures/meteogram.png");
+
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("meteo-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[derive(Clone)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub etag: Option<&'static str>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn ok(content_type: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status: 200,
+            content_type,
+            etag: None,
+            body: body.into(),
+        }
+    }
+
+    pub fn with_etag(self, etag: &'static str) -> Self {
+        Self {
+            etag: Some(etag),
+            ..self
+        }
+    }
+}
+
+/// A tiny HTTP/1.1 server that answers GET requests from a fixed route table
+/// and records every request target it sees. Routes with an ETag answer 304
+/// to a matching `If-None-Match`.
+pub struct MockServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockServer {
+    pub fn start(routes: Vec<(&'static str, Response)>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let routes: HashMap<_, _> = routes.into_iter().collect();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&requests);
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => continue,
+                };
+
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+
+                let mut if_none_match = None;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+
+                    if let Some((name, value)) = header.split_once(':') {
+                        if name.eq_ignore_ascii_case("if-none-match") {
+                            if_none_match = Some(value.trim().to_owned());
+                        }
+                    }
+                }
+
+                let target = request_line
+                    .split_whitespace()
+                    .nth(1)
+                    .unwrap_or("/")
+                    .to_owned();
+                let path = target.split('?').next().unwrap().to_owned();
+                recorded.lock().unwrap().push(target);
+
+                let mut response = routes.get(path.as_str()).cloned().unwrap_or(Response {
+                    status: 404,
+                    content_type: "text/plain",
+                    etag: None,
+                    body: b"not found".to_vec(),
+                });
+
+                if response.etag.is_some() && response.etag == if_none_match.as_deref() {
+                    response.status = 304;
+                    response.body.clear();
+                }
+
+                let etag = response
+                    .etag
+                    .map(|etag| format!("ETag: {}\r\n", etag))
+                    .unwrap_or_default();
+                let head = format!(
+                    "HTTP/1.1 {} X\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n",
+                    response.status,
+                    response.content_type,
+                    response.body.len(),
+                    etag
+                );
+                let _ = stream.write_all(head.as_bytes());
+                let _ = stream.write_all(&response.body);
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    /// Serves the autocomplete, forecast page and meteogram of Florianópolis.
+    pub fn cptec() -> Self {
+        Self::start(vec![
+            (
+                "/autocomplete",
+                Response::ok("application/json", CITIES_JSON),
+            ),
+            (
+                "/sc/florianopolis",
+                Response::ok("text/html", FORECAST_PAGE),
+            ),
+            (
+                "/meteogramas/4564.png",
+                Response::ok("image/png", METEOGRAM),
+            ),
+        ])
+    }
+
+    pub fn cptec_with_etags() -> Self {
+        Self::start(vec![
+            (
+                "/autocomplete",
+                Response::ok("application/json", CITIES_JSON).with_etag("\"cities\""),
+            ),
+            (
+                "/sc/florianopolis",
+                Response::ok("text/html", FORECAST_PAGE).with_etag("\"page\""),
+            ),
+            (
+                "/meteogramas/4564.png",
+                Response::ok("image/png", METEOGRAM).with_etag("\"meteogram\""),
+            ),
+        ])
+    }
+
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
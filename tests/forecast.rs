@@ -0,0 +1,85 @@
+mod common;
+
+use common::FORECAST_PAGE;
+use meteo::{scrape_forecast, DailyForecast, Forecast, ForecastChange};
+
+#[test]
+fn scrapes_daily_cards() {
+    let forecast = scrape_forecast(FORECAST_PAGE);
+
+    assert_eq!(
+        forecast.days,
+        [
+            DailyForecast {
+                date: "Seg 18/10".to_owned(),
+                condition: "Chuva isolada".to_owned(),
+                min_temperature: Some(19),
+                max_temperature: Some(28),
+                rain_probability: Some(70),
+                min_humidity: Some(60),
+                max_humidity: Some(95),
+                wind: Some("NE Moderado".to_owned()),
+                uv_index: Some(11.0),
+            },
+            DailyForecast {
+                date: "Ter 19/10".to_owned(),
+                condition: "Parcialmente nublado".to_owned(),
+                min_temperature: Some(-2),
+                max_temperature: Some(25),
+                rain_probability: Some(10),
+                min_humidity: Some(55),
+                max_humidity: Some(55),
+                wind: None,
+                uv_index: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn page_without_cards_has_no_days() {
+    assert!(scrape_forecast("<html></html>").days.is_empty());
+}
+
+#[test]
+fn detects_rain_crossing_and_condition_changes() {
+    let previous = scrape_forecast(FORECAST_PAGE);
+    let mut current = previous.clone();
+    current.days[0].rain_probability = Some(80);
+    current.days[1].rain_probability = Some(65);
+    current.days[1].condition = "Pancadas de chuva".to_owned();
+
+    assert_eq!(
+        current.changes_since(&previous, Some(60)),
+        [
+            ForecastChange::RainAbove {
+                date: "Ter 19/10".to_owned(),
+                previous: Some(10),
+                current: 65,
+            },
+            ForecastChange::Condition {
+                date: "Ter 19/10".to_owned(),
+                previous: "Parcialmente nublado".to_owned(),
+                current: "Pancadas de chuva".to_owned(),
+            },
+        ]
+    );
+    assert_eq!(current.changes_since(&previous, None).len(), 1);
+}
+
+#[test]
+fn first_forecast_only_alerts_on_rain() {
+    let current = scrape_forecast(FORECAST_PAGE);
+
+    let changes = current.changes_since(&Forecast::default(), Some(60));
+
+    assert_eq!(
+        changes,
+        [ForecastChange::RainAbove {
+            date: "Seg 18/10".to_owned(),
+            previous: None,
+            current: 70,
+        }]
+    );
+    assert!(current.changes_since(&current, Some(60)).is_empty());
+}
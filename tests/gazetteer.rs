@@ -0,0 +1,41 @@
+mod common;
+
+use meteo::{distance_km, CityIndex, Gazetteer};
+
+fn gazetteer() -> Gazetteer {
+    Gazetteer::from_csv(&include_bytes!("fixtures/municipios.csv")[..]).unwrap()
+}
+
+#[test]
+fn reads_ibge_csv() {
+    let gazetteer = gazetteer();
+    let places = gazetteer.places();
+
+    assert_eq!(places.len(), 3);
+    assert_eq!(places[0].name, "Florianópolis");
+    assert_eq!(places[0].uf, "SC");
+    assert_eq!(places[2].uf, "PR");
+}
+
+#[test]
+fn measures_great_circle_distance() {
+    let floripa = (-27.5945, -48.5477);
+    let curitiba = (-25.4284, -49.2733);
+
+    let distance = distance_km(floripa, curitiba);
+
+    assert!((distance - 251.0).abs() < 2.0, "{}", distance);
+}
+
+#[test]
+fn sorts_indexed_cities_by_distance() {
+    let mut cities: Vec<_> = serde_json::from_str(common::SAO_JOSE_JSON).unwrap();
+    cities.extend(serde_json::from_str::<Vec<_>>(common::CITIES_JSON).unwrap());
+    let index = CityIndex::new(cities);
+
+    let nearest = gazetteer().nearest(&index, -27.6, -48.62);
+
+    let ids: Vec<u32> = nearest.iter().map(|nearby| nearby.city.id).collect();
+    assert_eq!(ids, [5012, 4564, 5014]);
+    assert!(nearest[0].distance_km < 3.0);
+}
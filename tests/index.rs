@@ -0,0 +1,87 @@
+#![cfg(feature = "blocking")]
+
+mod common;
+
+use common::{temp_dir, MockServer, Response, SAO_JOSE_JSON};
+use meteo::{CityIndex, CptecClient, CrawlOptions};
+
+use std::time::Duration;
+
+fn crawl_options() -> CrawlOptions {
+    CrawlOptions {
+        alphabet: vec!['a', 's', ' '],
+        prefix_len: 1,
+        truncated_at: 3,
+        max_prefix_len: 2,
+        retries: 1,
+        delay: Duration::ZERO,
+    }
+}
+
+#[test]
+fn crawl_extends_truncated_prefixes() {
+    let server = MockServer::start(vec![(
+        "/autocomplete",
+        Response::ok("application/json", SAO_JOSE_JSON),
+    )]);
+    let client = CptecClient::with_base_url(&server.url).unwrap();
+
+    let mut prefixes = Vec::new();
+    let crawl = client.crawl_index(&crawl_options(), |prefix, found| {
+        prefixes.push((prefix.to_owned(), found))
+    });
+
+    let prefixes: Vec<_> = prefixes.iter().map(|(p, _)| p.as_str()).collect();
+    assert_eq!(prefixes, ["a", "s", "aa", "as", "a ", "sa", "ss", "s "]);
+    assert_eq!(crawl.truncated, ["aa", "as", "a ", "sa", "ss", "s "]);
+    assert_eq!(crawl.index.len(), 3);
+    assert!(server
+        .requests()
+        .contains(&"/autocomplete?term=a+".to_owned()));
+}
+
+#[test]
+fn crawl_retries_then_skips_failed_prefixes() {
+    let server = MockServer::start(vec![]);
+    let client = CptecClient::with_base_url(&server.url).unwrap();
+
+    let mut prefixes = Vec::new();
+    let crawl = client.crawl_index(&crawl_options(), |prefix, _| {
+        prefixes.push(prefix.to_owned())
+    });
+
+    let failed: Vec<_> = crawl.failed.iter().map(|(p, _)| p.as_str()).collect();
+    assert_eq!(failed, ["a", "s"]);
+    assert!(prefixes.is_empty());
+    assert!(crawl.index.is_empty());
+    assert_eq!(
+        server.requests(),
+        [
+            "/autocomplete?term=a",
+            "/autocomplete?term=a",
+            "/autocomplete?term=s",
+            "/autocomplete?term=s"
+        ]
+    );
+}
+
+#[test]
+fn index_answers_searches_without_network() {
+    let cities = serde_json::from_str(SAO_JOSE_JSON).unwrap();
+    let path = temp_dir("index").join("cities.json");
+    CityIndex::new(cities).save(&path).unwrap();
+
+    let client = CptecClient::with_base_url("http://127.0.0.1:9")
+        .unwrap()
+        .with_index(CityIndex::load(&path).unwrap());
+
+    let found: Vec<_> = client
+        .search("sao jose dos pinais")
+        .unwrap()
+        .into_iter()
+        .map(|city| city.id)
+        .collect();
+
+    assert_eq!(found[0], 5014);
+    assert!(client.search("curitiba").unwrap().is_empty());
+}
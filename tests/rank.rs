@@ -0,0 +1,42 @@
+mod common;
+
+use meteo::{normalize, rank_cities, City};
+
+fn names(cities: &[City]) -> Vec<String> {
+    cities.iter().map(City::label).collect()
+}
+
+#[test]
+fn normalizes_accents_case_and_separators() {
+    assert_eq!(normalize("  Florianópolis/SC "), "florianopolis/sc");
+    assert_eq!(normalize("SÃO  JOSÉ-dos-Pinhais"), "sao jose dos pinhais");
+}
+
+#[test]
+fn exact_name_ranks_first() {
+    let mut cities: Vec<City> = serde_json::from_str(common::SAO_JOSE_JSON).unwrap();
+    cities.reverse();
+
+    rank_cities("sao jose", &mut cities);
+
+    assert_eq!(
+        names(&cities),
+        [
+            "São José/SC",
+            "São José dos Pinhais/PR",
+            "São José do Rio Preto/SP"
+        ]
+    );
+}
+
+#[test]
+fn tolerates_typos_and_missing_accents() {
+    let mut cities: Vec<City> = serde_json::from_str(common::CITIES_JSON).unwrap();
+    cities.reverse();
+
+    rank_cities("florianoplis", &mut cities);
+    assert_eq!(cities[0].name, "Florianópolis");
+
+    rank_cities("SAO JOSE", &mut cities);
+    assert_eq!(cities[0].name, "São José");
+}